@@ -0,0 +1,150 @@
+use crate::models;
+use crate::shared_service::end_of_day;
+use rusqlite::{named_params, Connection};
+
+pub fn insert_account(
+    db: &Connection,
+    label: &str,
+    opening_balance: f64,
+) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "INSERT OR IGNORE INTO accounts (label, opening_balance) VALUES (:label, :opening_balance);",
+        named_params! {
+            ":label": label.trim(),
+            ":opening_balance": opening_balance,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn update_account(
+    db: &Connection,
+    id: i32,
+    label: &str,
+    opening_balance: f64,
+) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "
+        UPDATE accounts
+        SET label = (:label), opening_balance = (:opening_balance)
+        WHERE id = (:id);
+        ",
+        named_params! {
+            ":label": label.trim(),
+            ":opening_balance": opening_balance,
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+// Transactions of a deleted account are kept, they will no longer belong to any account
+pub fn delete_account(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "DELETE FROM accounts WHERE id = (:id);",
+        named_params! {
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn get_accounts(db: &Connection) -> Result<Vec<models::Account>, rusqlite::Error> {
+    let mut stmt = db.prepare("SELECT id, label, opening_balance FROM accounts;")?;
+    let mut rows = stmt.query([])?;
+    let mut accounts = Vec::new();
+    while let Some(row) = rows.next()? {
+        accounts.push(models::Account {
+            id: row.get(0)?,
+            label: row.get(1)?,
+            opening_balance: row.get(2)?,
+        });
+    }
+    Ok(accounts)
+}
+
+// Combined balance of every account at the end of the given date
+pub fn get_total_balance(db: &Connection, date: &str) -> Result<f64, rusqlite::Error> {
+    db.query_row(
+        "
+        SELECT
+        (SELECT COALESCE(SUM(opening_balance), 0) FROM accounts)
+        + (SELECT COALESCE(SUM(value), 0) FROM transactions WHERE date_created < (:end) AND deleted_at IS NULL)
+        ",
+        named_params! {
+            ":end": end_of_day(date)?,
+        },
+        |row| row.get(0),
+    )
+}
+
+pub fn get_account_balance(db: &Connection, id: i32, date: &str) -> Result<f64, rusqlite::Error> {
+    db.query_row(
+        "
+        SELECT
+        (SELECT COALESCE(SUM(opening_balance), 0) FROM accounts WHERE id = (:id))
+        + (
+            SELECT COALESCE(SUM(value), 0) FROM transactions
+            WHERE account_id = (:id) AND date_created < (:end) AND deleted_at IS NULL
+        )
+        ",
+        named_params! {
+            ":id": id,
+            ":end": end_of_day(date)?,
+        },
+        |row| row.get(0),
+    )
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn after_insert_should_be_readable() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_account(&conn, " checking ", 100.0)?;
+    let list = get_accounts(&conn)?;
+
+    assert!(list.len() == 1, "Expected list with one item, got {:?}", list);
+    assert_eq!(list[0].label, "checking");
+    assert_eq!(list[0].opening_balance, 100.0);
+    Ok(())
+}
+
+#[test]
+fn should_be_able_to_update_account() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_account(&conn, "checking", 100.0)?;
+    let id = get_accounts(&conn)?[0].id;
+
+    update_account(&conn, id, "savings", 50.0)?;
+    let list = get_accounts(&conn)?;
+
+    assert_eq!(list[0].label, "savings");
+    assert_eq!(list[0].opening_balance, 50.0);
+    Ok(())
+}
+
+#[test]
+fn delete_should_keep_transactions_without_account() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_account(&conn, "checking", 100.0)?;
+    let id = get_accounts(&conn)?[0].id;
+    crate::transaction_service::insert_transaction(
+        &conn,
+        1.0,
+        "test",
+        None,
+        "2023-11-01",
+        Some(id),
+        vec![],
+    )?;
+
+    delete_account(&conn, id)?;
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert!(get_accounts(&conn)?.is_empty());
+    assert_eq!(page.transactions.len(), 1);
+    assert_eq!(page.transactions[0].account_id, None);
+    Ok(())
+}
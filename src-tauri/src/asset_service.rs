@@ -0,0 +1,229 @@
+use crate::models::{Asset, AssetKind, AssetValuation};
+use crate::shared_service::parse_date;
+use rusqlite::{named_params, Connection};
+
+fn kind_to_str(kind: AssetKind) -> &'static str {
+    match kind {
+        AssetKind::ASSET => "ASSET",
+        AssetKind::LIABILITY => "LIABILITY",
+    }
+}
+
+fn kind_from_str(kind: &str) -> AssetKind {
+    match kind {
+        "LIABILITY" => AssetKind::LIABILITY,
+        _ => AssetKind::ASSET,
+    }
+}
+
+pub fn insert_asset(db: &Connection, label: &str, kind: AssetKind) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "INSERT OR IGNORE INTO assets (label, kind) VALUES (:label, :kind);",
+        named_params! {
+            ":label": label.trim(),
+            ":kind": kind_to_str(kind),
+        },
+    )?;
+    Ok(())
+}
+
+pub fn update_asset_label(db: &Connection, id: i32, label: &str) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "UPDATE assets SET label = (:label) WHERE id = (:id);",
+        named_params! {
+            ":label": label.trim(),
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn delete_asset(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "DELETE FROM assets WHERE id = (:id);",
+        named_params! {
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn get_assets(db: &Connection) -> Result<Vec<Asset>, rusqlite::Error> {
+    let mut stmt = db.prepare("SELECT id, label, kind FROM assets;")?;
+    let mut rows = stmt.query([])?;
+    let mut assets = Vec::new();
+    while let Some(row) = rows.next()? {
+        let kind: String = row.get(2)?;
+        assets.push(Asset {
+            id: row.get(0)?,
+            label: row.get(1)?,
+            kind: kind_from_str(&kind),
+        });
+    }
+    Ok(assets)
+}
+
+// A valuation is the value of the asset from the given date onwards,
+// setting a valuation for an existing date replaces it
+pub fn set_asset_valuation(
+    db: &Connection,
+    asset_id: i32,
+    date: &str,
+    value: f64,
+) -> Result<(), rusqlite::Error> {
+    let date = parse_date(date)?;
+    db.execute(
+        "
+        INSERT INTO asset_valuations (asset_id, date, value)
+        VALUES (:asset_id, :date, :value)
+        ON CONFLICT (asset_id, date) DO UPDATE SET value = excluded.value;
+        ",
+        named_params! {
+            ":asset_id": asset_id,
+            ":date": date.format("%Y-%m-%d").to_string(),
+            ":value": value,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn delete_asset_valuation(
+    db: &Connection,
+    asset_id: i32,
+    date: &str,
+) -> Result<(), rusqlite::Error> {
+    let date = parse_date(date)?;
+    db.execute(
+        "DELETE FROM asset_valuations WHERE asset_id = (:asset_id) AND date = (:date);",
+        named_params! {
+            ":asset_id": asset_id,
+            ":date": date.format("%Y-%m-%d").to_string(),
+        },
+    )?;
+    Ok(())
+}
+
+pub fn get_asset_valuations(
+    db: &Connection,
+    asset_id: i32,
+) -> Result<Vec<AssetValuation>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "
+        SELECT asset_id, date, value FROM asset_valuations
+        WHERE asset_id = (:asset_id)
+        ORDER BY date;
+        ",
+    )?;
+    let mut rows = stmt.query(named_params! {
+        ":asset_id": asset_id,
+    })?;
+    let mut valuations = Vec::new();
+    while let Some(row) = rows.next()? {
+        valuations.push(AssetValuation {
+            asset_id: row.get(0)?,
+            date: row.get(1)?,
+            value: row.get(2)?,
+        });
+    }
+    Ok(valuations)
+}
+
+// Sum of the latest valuations on or before the given date, returned as (assets, liabilities)
+pub fn get_valuation_totals(db: &Connection, date: &str) -> Result<(f64, f64), rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "
+        SELECT a.kind, SUM(v.value)
+        FROM assets a
+        INNER JOIN asset_valuations v ON v.asset_id = a.id
+        WHERE v.date = (
+            SELECT MAX(latest.date) FROM asset_valuations latest
+            WHERE latest.asset_id = a.id AND latest.date <= (:date)
+        )
+        GROUP BY a.kind
+        ",
+    )?;
+    let mut rows = stmt.query(named_params! {
+        ":date": date,
+    })?;
+
+    let mut assets = 0.0;
+    let mut liabilities = 0.0;
+    while let Some(row) = rows.next()? {
+        let kind: String = row.get(0)?;
+        let sum: f64 = row.get(1)?;
+        match kind_from_str(&kind) {
+            AssetKind::ASSET => assets = sum,
+            AssetKind::LIABILITY => liabilities = sum,
+        }
+    }
+    Ok((assets, liabilities))
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn after_insert_should_be_readable() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_asset(&conn, "house", AssetKind::ASSET)?;
+    insert_asset(&conn, "mortgage", AssetKind::LIABILITY)?;
+    let list = get_assets(&conn)?;
+
+    assert!(list.len() == 2, "Expected list with two items, got {:?}", list);
+    assert_eq!(list[0].kind, AssetKind::ASSET);
+    assert_eq!(list[1].kind, AssetKind::LIABILITY);
+    Ok(())
+}
+
+#[test]
+fn setting_valuation_for_same_date_should_replace_it() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_asset(&conn, "car", AssetKind::ASSET)?;
+    let id = get_assets(&conn)?[0].id;
+
+    set_asset_valuation(&conn, id, "2023-11-01", 10000.0)?;
+    set_asset_valuation(&conn, id, "2023-11-01", 9000.0)?;
+    set_asset_valuation(&conn, id, "2023-12-01", 8500.0)?;
+
+    let valuations = get_asset_valuations(&conn, id)?;
+    assert!(valuations.len() == 2, "Expected two valuations, got {:?}", valuations);
+    assert_eq!(valuations[0].value, 9000.0);
+    assert_eq!(valuations[1].value, 8500.0);
+
+    // the date is read the same way when deleting
+    delete_asset_valuation(&conn, id, " 2023-12-01 ")?;
+    assert_eq!(get_asset_valuations(&conn, id)?.len(), 1);
+    assert!(delete_asset_valuation(&conn, id, "2023-12-32").is_err());
+    Ok(())
+}
+
+#[test]
+fn valuation_totals_should_use_latest_valuation() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_asset(&conn, "house", AssetKind::ASSET)?;
+    insert_asset(&conn, "mortgage", AssetKind::LIABILITY)?;
+    let assets = get_assets(&conn)?;
+
+    set_asset_valuation(&conn, assets[0].id, "2023-01-01", 200000.0)?;
+    set_asset_valuation(&conn, assets[0].id, "2023-06-01", 210000.0)?;
+    set_asset_valuation(&conn, assets[1].id, "2023-03-01", 150000.0)?;
+
+    assert_eq!(get_valuation_totals(&conn, "2022-12-31")?, (0.0, 0.0));
+    assert_eq!(get_valuation_totals(&conn, "2023-02-01")?, (200000.0, 0.0));
+    assert_eq!(get_valuation_totals(&conn, "2023-07-01")?, (210000.0, 150000.0));
+    Ok(())
+}
+
+#[test]
+fn delete_should_remove_valuations() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_asset(&conn, "car", AssetKind::ASSET)?;
+    let id = get_assets(&conn)?[0].id;
+    set_asset_valuation(&conn, id, "2023-11-01", 10000.0)?;
+
+    delete_asset(&conn, id)?;
+
+    assert!(get_assets(&conn)?.is_empty());
+    assert!(get_asset_valuations(&conn, id)?.is_empty());
+    Ok(())
+}
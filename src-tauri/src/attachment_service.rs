@@ -0,0 +1,278 @@
+use crate::models::Attachment;
+use rusqlite::{named_params, Connection};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Attachments are stored in a directory next to the database. It is named after the whole file
+// name so that databases like budget.sqlite and budget.db in one folder don't share it.
+pub fn attachments_dir(database: &Path) -> PathBuf {
+    let mut name = database.file_name().unwrap_or_default().to_os_string();
+    name.push(".attachments");
+    database.with_file_name(name)
+}
+
+// Directory used by older versions, named by replacing the extension of the database
+pub fn legacy_attachments_dir(database: &Path) -> PathBuf {
+    database.with_extension("attachments")
+}
+
+// Stored files are named by the sha256 of their content, other files are not ours
+fn is_hash(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+// Files are stored in the attachment directory named by the sha256 of their content,
+// so the same receipt attached twice is only stored once
+pub fn add_attachment(
+    db: &Connection,
+    dir: &Path,
+    transaction_id: i32,
+    file_name: &str,
+    content: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    let hash = Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(&hash);
+    if !path.exists() {
+        fs::write(&path, content)?;
+    }
+
+    db.execute(
+        "
+        INSERT INTO attachments (transaction_id, file_name, hash, size)
+        VALUES (:transaction_id, :file_name, :hash, :size);
+        ",
+        named_params! {
+            ":transaction_id": transaction_id,
+            ":file_name": file_name,
+            ":hash": hash,
+            ":size": content.len(),
+        },
+    )?;
+    Ok(())
+}
+
+pub fn get_attachments(
+    db: &Connection,
+    transaction_id: i32,
+) -> Result<Vec<Attachment>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "
+        SELECT id, transaction_id, file_name, hash, size, date_added
+        FROM attachments
+        WHERE transaction_id = (:transaction_id);
+        ",
+    )?;
+    let mut rows = stmt.query(named_params! {
+        ":transaction_id": transaction_id,
+    })?;
+    let mut attachments = Vec::new();
+    while let Some(row) = rows.next()? {
+        attachments.push(Attachment {
+            id: row.get(0)?,
+            transaction_id: row.get(1)?,
+            file_name: row.get(2)?,
+            hash: row.get(3)?,
+            size: row.get(4)?,
+            date_added: row.get(5)?,
+        });
+    }
+    Ok(attachments)
+}
+
+pub fn get_attachment_path(
+    db: &Connection,
+    dir: &Path,
+    id: i32,
+) -> Result<PathBuf, rusqlite::Error> {
+    let hash: String = db.query_row(
+        "SELECT hash FROM attachments WHERE id = (:id);",
+        named_params! {
+            ":id": id,
+        },
+        |row| row.get(0),
+    )?;
+    Ok(dir.join(hash))
+}
+
+pub fn remove_attachment(db: &Connection, dir: &Path, id: i32) -> Result<(), Box<dyn Error>> {
+    db.execute(
+        "DELETE FROM attachments WHERE id = (:id);",
+        named_params! {
+            ":id": id,
+        },
+    )?;
+    remove_orphaned_files(db, dir)?;
+    Ok(())
+}
+
+// Copies the files of the database from the directory of older versions, which may be shared with
+// another database of the same name, and removes the files no longer referenced. Returns the
+// attachment directory of the database.
+pub fn prepare_attachments(db: &Connection, database: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = attachments_dir(database);
+    let legacy_dir = legacy_attachments_dir(database);
+    if legacy_dir.is_dir() {
+        let mut stmt = db.prepare("SELECT DISTINCT hash FROM attachments;")?;
+        let hashes = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+        for hash in hashes.iter().filter(|hash| is_hash(hash)) {
+            if legacy_dir.join(hash).is_file() && !dir.join(hash).exists() {
+                fs::create_dir_all(&dir)?;
+                fs::copy(legacy_dir.join(hash), dir.join(hash))?;
+            }
+        }
+    }
+
+    remove_orphaned_files(db, &dir)?;
+    Ok(dir)
+}
+
+// Removes stored files which are no longer referenced by any attachment, the rows themselves
+// are removed when their transaction is purged from the trash. Files not named like a stored
+// file are left alone. Returns the number of removed files.
+pub fn remove_orphaned_files(db: &Connection, dir: &Path) -> Result<usize, Box<dyn Error>> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut stmt = db.prepare("SELECT COUNT(*) FROM attachments WHERE hash = (:hash);")?;
+    let mut removed = 0;
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let hash = match path.file_name().and_then(|name| name.to_str()) {
+            Some(hash) if is_hash(hash) => hash.to_string(),
+            _ => continue,
+        };
+
+        let references: i32 = stmt.query_row(named_params! { ":hash": hash }, |row| row.get(0))?;
+        if references == 0 && path.is_file() {
+            fs::remove_file(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+#[cfg(test)]
+use crate::shared_service::TestDir;
+
+#[test]
+fn added_attachment_should_be_readable() -> Result<(), Box<dyn Error>> {
+    let conn = init_db_in_memory()?;
+    let dir = TestDir::new("added_attachment_should_be_readable");
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test", None, "2023-11-01", None, vec![])?;
+
+    add_attachment(&conn, &dir, 1, "receipt.pdf", b"receipt")?;
+
+    let attachments = get_attachments(&conn, 1)?;
+    assert_eq!(attachments.len(), 1);
+    assert_eq!(attachments[0].file_name, "receipt.pdf");
+    assert_eq!(attachments[0].size, 7);
+
+    let path = get_attachment_path(&conn, &dir, attachments[0].id)?;
+    assert_eq!(fs::read(path)?, b"receipt");
+    Ok(())
+}
+
+#[test]
+fn same_content_should_be_stored_once() -> Result<(), Box<dyn Error>> {
+    let conn = init_db_in_memory()?;
+    let dir = TestDir::new("same_content_should_be_stored_once");
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test1", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test2", None, "2023-11-01", None, vec![])?;
+
+    add_attachment(&conn, &dir, 1, "receipt.pdf", b"receipt")?;
+    add_attachment(&conn, &dir, 2, "copy.pdf", b"receipt")?;
+    assert_eq!(fs::read_dir(&*dir)?.count(), 1);
+
+    // the file is still used by the other transaction
+    let id = get_attachments(&conn, 1)?[0].id;
+    remove_attachment(&conn, &dir, id)?;
+    assert!(get_attachments(&conn, 1)?.is_empty());
+    assert_eq!(fs::read_dir(&*dir)?.count(), 1);
+
+    let id = get_attachments(&conn, 2)?[0].id;
+    remove_attachment(&conn, &dir, id)?;
+    assert_eq!(fs::read_dir(&*dir)?.count(), 0);
+    Ok(())
+}
+
+#[test]
+fn purging_transaction_should_clean_up_files() -> Result<(), Box<dyn Error>> {
+    let conn = init_db_in_memory()?;
+    let dir = TestDir::new("purging_transaction_should_clean_up_files");
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test", None, "2023-11-01", None, vec![])?;
+    add_attachment(&conn, &dir, 1, "receipt.pdf", b"receipt")?;
+    add_attachment(&conn, &dir, 1, "invoice.pdf", b"invoice")?;
+
+    // the attachments stay while the transaction is in the trash
+    crate::transaction_service::delete_transaction(&conn, 1)?;
+    assert_eq!(get_attachments(&conn, 1)?.len(), 2);
+    assert_eq!(remove_orphaned_files(&conn, &dir)?, 0);
+
+    // files which were not stored as attachments are kept
+    fs::write(dir.join("notes.txt"), b"notes")?;
+
+    crate::trash_service::purge_trash(&conn, 0)?;
+    assert!(get_attachments(&conn, 1)?.is_empty());
+    assert_eq!(remove_orphaned_files(&conn, &dir)?, 2);
+    assert_eq!(fs::read_dir(&*dir)?.count(), 1);
+    Ok(())
+}
+
+#[test]
+fn databases_with_same_name_should_keep_their_files() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("databases_with_same_name_should_keep_their_files");
+    let mut databases = Vec::new();
+    for (file_name, content) in [("budget.sqlite", b"receipt"), ("budget.db", b"invoice")] {
+        let path = dir.join(file_name);
+        let db = crate::encryption_service::open_database(&path, None)?;
+        crate::migration_service::init_tables(&db)?;
+        crate::transaction_service::insert_transaction(&db, -10.0, "test", None, "2023-11-01", None, vec![])?;
+        add_attachment(&db, &attachments_dir(&path), 1, "file.pdf", content)?;
+        databases.push((path, db));
+    }
+
+    // opening the databases in turn keeps the files of the other one
+    for _ in 0..2 {
+        for (path, db) in &databases {
+            prepare_attachments(db, path)?;
+        }
+    }
+    for (path, db) in &databases {
+        let id = get_attachments(db, 1)?[0].id;
+        assert!(get_attachment_path(db, &attachments_dir(path), id)?.exists());
+    }
+    Ok(())
+}
+
+#[test]
+fn files_of_legacy_directory_should_be_copied() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("files_of_legacy_directory_should_be_copied");
+    let path = dir.join("budget.sqlite");
+    let db = crate::encryption_service::open_database(&path, None)?;
+    crate::migration_service::init_tables(&db)?;
+    crate::transaction_service::insert_transaction(&db, -10.0, "test", None, "2023-11-01", None, vec![])?;
+    add_attachment(&db, &legacy_attachments_dir(&path), 1, "receipt.pdf", b"receipt")?;
+    // a file of another database using the same directory
+    let other = init_db_in_memory()?;
+    crate::transaction_service::insert_transaction(&other, -10.0, "test", None, "2023-11-01", None, vec![])?;
+    add_attachment(&other, &legacy_attachments_dir(&path), 1, "invoice.pdf", b"invoice")?;
+
+    let attachments = prepare_attachments(&db, &path)?;
+    assert_eq!(fs::read(get_attachment_path(&db, &attachments, get_attachments(&db, 1)?[0].id)?)?, b"receipt");
+    assert_eq!(fs::read_dir(&attachments)?.count(), 1);
+    // the legacy directory may still be used by the other database
+    assert_eq!(fs::read_dir(legacy_attachments_dir(&path))?.count(), 2);
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use crate::models::AuditEntry;
+use crate::shared_service::parse_date;
+use rusqlite::{named_params, Connection};
+
+// Tables whose changes are logged, with the column identifying the entity the row belongs to.
+// Settings are identified by their key instead of a numeric id.
+const AUDITED_TABLES: &[(&str, &str)] = &[
+    ("transactions", "id"),
+    ("categories", "id"),
+    ("transaction_categories", "transaction_id"),
+    ("accounts", "id"),
+    ("assets", "id"),
+    ("asset_valuations", "asset_id"),
+    ("recurring_transactions", "id"),
+    ("payees", "id"),
+    ("payee_patterns", "payee_id"),
+    ("tags", "id"),
+    ("transaction_tags", "transaction_id"),
+    ("attachments", "id"),
+    ("settings", "key"),
+];
+
+fn row_json(db: &Connection, table: &str, row: &str) -> Result<String, rusqlite::Error> {
+    let mut stmt = db.prepare("SELECT name FROM pragma_table_info(:table);")?;
+    let columns = stmt
+        .query_map(named_params! { ":table": table }, |row| row.get(0))?
+        .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+
+    let fields: Vec<String> = columns
+        .iter()
+        .map(|column| format!("'{}', {}.{}", column, row, column))
+        .collect();
+    Ok(format!("json_object({})", fields.join(", ")))
+}
+
+// Every insert, update and delete on the audited tables is logged by triggers, including
+// cascades and changes made by undo, redo and purging the trash. The triggers are recreated
+// on every start so they include the columns added by migrations.
+pub fn create_audit_triggers(db: &Connection) -> Result<(), rusqlite::Error> {
+    for (table, key) in AUDITED_TABLES {
+        let old = row_json(db, table, "OLD")?;
+        let new = row_json(db, table, "NEW")?;
+
+        db.execute_batch(&format!(
+            "
+            DROP TRIGGER IF EXISTS audit_{table}_insert;
+            DROP TRIGGER IF EXISTS audit_{table}_update;
+            DROP TRIGGER IF EXISTS audit_{table}_delete;
+
+            CREATE TRIGGER audit_{table}_insert AFTER INSERT ON {table}
+            BEGIN
+                INSERT INTO audit_log (entity, entity_id, operation, before, after)
+                VALUES ('{table}', NEW.{key}, 'INSERT', NULL, {new});
+            END;
+
+            CREATE TRIGGER audit_{table}_update AFTER UPDATE ON {table}
+            BEGIN
+                INSERT INTO audit_log (entity, entity_id, operation, before, after)
+                VALUES ('{table}', NEW.{key}, 'UPDATE', {old}, {new});
+            END;
+
+            CREATE TRIGGER audit_{table}_delete AFTER DELETE ON {table}
+            BEGIN
+                INSERT INTO audit_log (entity, entity_id, operation, before, after)
+                VALUES ('{table}', OLD.{key}, 'DELETE', {old}, NULL);
+            END;
+            ",
+        ))?;
+    }
+    Ok(())
+}
+
+// Entries are filtered by the entity (table name) and id when given, and by the days between
+// the start and end date. Ids are passed and returned as text so that they can be the key of
+// a setting as well. Newest entries are returned first.
+pub fn get_audit_log(
+    db: &Connection,
+    entity: Option<&str>,
+    entity_id: Option<&str>,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<Vec<AuditEntry>, rusqlite::Error> {
+    let start_date = start_date.map(parse_date).transpose()?;
+    let end_date = end_date.map(parse_date).transpose()?;
+
+    let mut stmt = db.prepare(
+        "
+        SELECT id, entity, CAST(entity_id AS TEXT), operation, before, after, timestamp
+        FROM audit_log
+        WHERE ((:entity) IS NULL OR entity = (:entity))
+        AND ((:entity_id) IS NULL OR entity_id = (:entity_id))
+        AND ((:start_date) IS NULL OR date(timestamp) >= (:start_date))
+        AND ((:end_date) IS NULL OR date(timestamp) <= (:end_date))
+        ORDER BY id DESC;
+        ",
+    )?;
+    let mut rows = stmt.query(named_params! {
+        ":entity": entity,
+        ":entity_id": entity_id,
+        ":start_date": start_date.map(|date| date.format("%Y-%m-%d").to_string()),
+        ":end_date": end_date.map(|date| date.format("%Y-%m-%d").to_string()),
+    })?;
+
+    let mut entries = Vec::new();
+    while let Some(row) = rows.next()? {
+        entries.push(AuditEntry {
+            id: row.get(0)?,
+            entity: row.get(1)?,
+            entity_id: row.get(2)?,
+            operation: row.get(3)?,
+            before: row.get(4)?,
+            after: row.get(5)?,
+            timestamp: row.get(6)?,
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn mutations_should_be_logged_with_before_and_after() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::account_service::insert_account(&conn, "checking", 100.0)?;
+    crate::account_service::update_account(&conn, 1, "savings", 100.0)?;
+    crate::account_service::delete_account(&conn, 1)?;
+
+    let entries = get_audit_log(&conn, Some("accounts"), Some("1"), None, None)?;
+    let operations: Vec<&str> = entries.iter().map(|entry| entry.operation.as_str()).collect();
+    assert_eq!(operations, vec!["DELETE", "UPDATE", "INSERT"]);
+
+    assert_eq!(entries[1].before.as_ref().unwrap()["label"], "checking");
+    assert_eq!(entries[1].after.as_ref().unwrap()["label"], "savings");
+    assert_eq!(entries[1].after.as_ref().unwrap()["opening_balance"], 100.0);
+    assert!(entries[0].after.is_none());
+    assert!(entries[2].before.is_none());
+    Ok(())
+}
+
+#[test]
+fn cascaded_deletes_should_be_logged() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::tag_service::insert_tag(&conn, "holiday")?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test", None, "2023-11-01", None, vec![])?;
+    crate::tag_service::add_transaction_tag(&conn, 1, 1)?;
+    crate::tag_service::delete_tag(&conn, 1)?;
+
+    let entries = get_audit_log(&conn, Some("transaction_tags"), None, None, None)?;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].operation, "DELETE");
+    assert_eq!(entries[0].entity_id.as_deref(), Some("1"));
+    Ok(())
+}
+
+#[test]
+fn audit_log_should_filter_by_date() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    conn.execute("UPDATE audit_log SET timestamp = '2023-11-01 12:00:00';", [])?;
+    crate::category_service::insert_category(&conn, "travel")?;
+
+    let entries = get_audit_log(&conn, None, None, Some("2023-11-01"), Some("2023-11-01"))?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].after.as_ref().unwrap()["label"], "food");
+
+    let entries = get_audit_log(&conn, Some("categories"), None, Some("2023-11-02"), None)?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].after.as_ref().unwrap()["label"], "travel");
+
+    assert!(get_audit_log(&conn, None, None, Some("2023-13-01"), None).is_err());
+    Ok(())
+}
+
+#[test]
+fn settings_should_be_logged_by_key() -> Result<(), Box<dyn std::error::Error>> {
+    let conn = init_db_in_memory()?;
+    crate::settings_service::set_setting(&conn, "page_size", &serde_json::json!(50))?;
+    crate::settings_service::set_setting(&conn, "page_size", &serde_json::json!(20))?;
+    crate::settings_service::reset_setting(&conn, "page_size")?;
+
+    let entries = get_audit_log(&conn, Some("settings"), Some("page_size"), None, None)?;
+    let operations: Vec<&str> = entries.iter().map(|entry| entry.operation.as_str()).collect();
+    assert_eq!(operations, vec!["DELETE", "UPDATE", "INSERT"]);
+    assert_eq!(entries[1].before.as_ref().unwrap()["value"], "50");
+    assert_eq!(entries[1].after.as_ref().unwrap()["value"], "20");
+    assert_eq!(entries[0].entity_id.as_deref(), Some("page_size"));
+    Ok(())
+}
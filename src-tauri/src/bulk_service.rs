@@ -0,0 +1,258 @@
+use crate::journal_service::{Change, Entity};
+use crate::models::{BulkOperation, TransactionFilter, TransactionSelection};
+use crate::shared_service::{format_date_time, parse_date_time, to_array};
+use crate::transaction_service::{FilterParams, FILTER_CONDITIONS};
+use rusqlite::{named_params, Connection};
+
+// Ids of the transactions matching the filter, the same way the transaction list filters them
+fn query_filtered_ids(db: &Connection, filter: &TransactionFilter) -> Result<Vec<i32>, rusqlite::Error> {
+    let filter_params = FilterParams::new(filter);
+    let mut stmt = db.prepare(&format!(
+        "SELECT t.id FROM transactions as t WHERE {} ORDER BY t.id",
+        FILTER_CONDITIONS
+    ))?;
+    let selected = stmt
+        .query_map(&*filter_params.with(&[]), |row| row.get(0))?
+        .collect::<Result<Vec<i32>, rusqlite::Error>>()?;
+    Ok(selected)
+}
+
+fn selected_ids(db: &Connection, selection: &TransactionSelection) -> Result<Vec<i32>, rusqlite::Error> {
+    match selection {
+        TransactionSelection::FILTER(filter) => query_filtered_ids(db, filter),
+        TransactionSelection::IDS(ids) => {
+            let mut stmt = db.prepare(
+                "
+                SELECT id FROM transactions
+                WHERE id IN rarray(:ids) AND deleted_at IS NULL
+                ORDER BY id
+                ",
+            )?;
+            let selected = stmt
+                .query_map(named_params! { ":ids": to_array(ids) }, |row| row.get(0))?
+                .collect::<Result<Vec<i32>, rusqlite::Error>>()?;
+            Ok(selected)
+        }
+    }
+}
+
+fn category_exists(db: &Connection, id: i32) -> Result<bool, rusqlite::Error> {
+    let count: i32 = db.query_row(
+        "SELECT COUNT(*) FROM categories WHERE id = (:id) AND deleted_at IS NULL;",
+        named_params! { ":id": id },
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn add_category(db: &Connection, transaction_id: i32, category_id: i32) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "
+        INSERT OR IGNORE INTO transaction_categories(transaction_id, category_id)
+        VALUES(:transaction_id, :category_id);
+        ",
+        named_params! {
+            ":transaction_id": transaction_id,
+            ":category_id": category_id,
+        },
+    )?;
+    Ok(())
+}
+
+fn apply(db: &Connection, id: i32, operation: &BulkOperation) -> Result<(), rusqlite::Error> {
+    match operation {
+        BulkOperation::DELETE => {
+            db.execute(
+                "UPDATE transactions SET deleted_at = CURRENT_TIMESTAMP WHERE id = (:id);",
+                named_params! { ":id": id },
+            )?;
+        }
+        BulkOperation::ADD_CATEGORY(category_id) => {
+            if category_exists(db, *category_id)? {
+                add_category(db, id, *category_id)?;
+            }
+        }
+        BulkOperation::REMOVE_CATEGORY(category_id) => {
+            db.execute(
+                "
+                DELETE FROM transaction_categories
+                WHERE transaction_id = (:transaction_id) AND category_id = (:category_id);
+                ",
+                named_params! {
+                    ":transaction_id": id,
+                    ":category_id": category_id,
+                },
+            )?;
+        }
+        BulkOperation::REPLACE_CATEGORIES(category_ids) => {
+            db.execute(
+                "DELETE FROM transaction_categories WHERE transaction_id = (:id);",
+                named_params! { ":id": id },
+            )?;
+            for category_id in category_ids {
+                if category_exists(db, *category_id)? {
+                    add_category(db, id, *category_id)?;
+                }
+            }
+        }
+        BulkOperation::SET_DATE(date) => {
+            let (date, time) = parse_date_time(date)?;
+            db.execute(
+                "UPDATE transactions SET date_created = (:date_created) WHERE id = (:id);",
+                named_params! {
+                    ":date_created": format_date_time(date, time),
+                    ":id": id,
+                },
+            )?;
+        }
+        BulkOperation::MOVE_TO_ACCOUNT(account_id) => {
+            db.execute(
+                "UPDATE transactions SET account_id = (:account_id) WHERE id = (:id);",
+                named_params! {
+                    ":account_id": account_id,
+                    ":id": id,
+                },
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn validate(db: &Connection, operation: &BulkOperation) -> Result<(), rusqlite::Error> {
+    match operation {
+        BulkOperation::SET_DATE(date) => {
+            parse_date_time(date)?;
+        }
+        BulkOperation::MOVE_TO_ACCOUNT(Some(account_id)) => {
+            db.query_row(
+                "SELECT id FROM accounts WHERE id = (:id);",
+                named_params! { ":id": account_id },
+                |row| row.get::<_, i32>(0),
+            )?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+// Applies the operation to every selected transaction as one undoable operation, either all
+// of the transactions are changed or none. Returns the number of transactions that changed,
+// for example adding a category a transaction already has does not count.
+pub fn bulk_update(
+    db: &Connection,
+    selection: &TransactionSelection,
+    operation: &BulkOperation,
+) -> Result<usize, rusqlite::Error> {
+    validate(db, operation)?;
+
+    crate::shared_service::in_savepoint(db, || {
+        let mut changes = Vec::new();
+        for id in selected_ids(db, selection)? {
+            let before = crate::journal_service::snapshot(db, Entity::Transaction, id)?;
+            apply(db, id, operation)?;
+            if crate::journal_service::snapshot(db, Entity::Transaction, id)? == before {
+                continue;
+            }
+
+            db.execute(
+                "UPDATE transactions SET updated_at = CURRENT_TIMESTAMP WHERE id = (:id);",
+                named_params! { ":id": id },
+            )?;
+            changes.push(Change {
+                entity: Entity::Transaction,
+                entity_id: id,
+                before,
+                after: crate::journal_service::snapshot(db, Entity::Transaction, id)?,
+            });
+        }
+
+        let affected = changes.len();
+        if affected > 0 {
+            crate::journal_service::record(db, "bulk_update", changes)?;
+        }
+        Ok(affected)
+    })
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[cfg(test)]
+fn init_transactions(conn: &Connection) -> Result<(), rusqlite::Error> {
+    crate::category_service::insert_category(conn, "food")?;
+    crate::category_service::insert_category(conn, "rent")?;
+    crate::transaction_service::insert_transaction(conn, -10.0, "store", None, "2023-11-01", None, vec![1])?;
+    crate::transaction_service::insert_transaction(conn, -20.0, "store", None, "2023-11-02", None, vec![])?;
+    crate::transaction_service::insert_transaction(conn, -500.0, "landlord", None, "2023-11-03", None, vec![2])?;
+    Ok(())
+}
+
+#[cfg(test)]
+fn category_ids(conn: &Connection, id: i32) -> Result<Vec<i32>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT category_id FROM transaction_categories WHERE transaction_id = (:id) ORDER BY category_id;",
+    )?;
+    let ids = stmt
+        .query_map(named_params! { ":id": id }, |row| row.get(0))?
+        .collect::<Result<Vec<i32>, rusqlite::Error>>()?;
+    Ok(ids)
+}
+
+#[test]
+fn bulk_category_operations_should_count_changed_transactions() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    init_transactions(&conn)?;
+
+    let selection = TransactionSelection::IDS(vec![1, 2, 3]);
+    assert_eq!(bulk_update(&conn, &selection, &BulkOperation::ADD_CATEGORY(1))?, 2);
+    assert_eq!(category_ids(&conn, 3)?, vec![1, 2]);
+
+    assert_eq!(bulk_update(&conn, &selection, &BulkOperation::REMOVE_CATEGORY(2))?, 1);
+    assert_eq!(bulk_update(&conn, &selection, &BulkOperation::REPLACE_CATEGORIES(vec![2]))?, 3);
+    assert_eq!(category_ids(&conn, 1)?, vec![2]);
+
+    // missing categories are skipped like when inserting a transaction
+    assert_eq!(bulk_update(&conn, &selection, &BulkOperation::ADD_CATEGORY(9))?, 0);
+    Ok(())
+}
+
+#[test]
+fn bulk_operation_should_apply_to_filtered_transactions() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    init_transactions(&conn)?;
+    crate::account_service::insert_account(&conn, "bank", 0.0)?;
+
+    let selection = TransactionSelection::FILTER(TransactionFilter {
+        search: "store".to_string(),
+        ..Default::default()
+    });
+    assert_eq!(bulk_update(&conn, &selection, &BulkOperation::MOVE_TO_ACCOUNT(Some(1)))?, 2);
+    assert_eq!(bulk_update(&conn, &selection, &BulkOperation::SET_DATE("2023-12-01".to_string()))?, 2);
+    assert_eq!(bulk_update(&conn, &selection, &BulkOperation::DELETE)?, 2);
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions.len(), 1);
+    assert_eq!(page.transactions[0].name, "landlord");
+    assert_eq!(page.transactions[0].account_id, None);
+
+    // the whole bulk operation is undone at once
+    crate::journal_service::undo(&conn)?;
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "store", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions.len(), 2);
+    assert!(page.transactions.iter().all(|t| t.account_id == Some(1)));
+    Ok(())
+}
+
+#[test]
+fn invalid_bulk_operation_should_not_change_anything() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    init_transactions(&conn)?;
+
+    let selection = TransactionSelection::IDS(vec![1, 2]);
+    assert!(bulk_update(&conn, &selection, &BulkOperation::SET_DATE("2023-11-31".to_string())).is_err());
+    assert!(bulk_update(&conn, &selection, &BulkOperation::MOVE_TO_ACCOUNT(Some(5))).is_err());
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions[0].date_created.to_string(), "2023-11-01");
+    Ok(())
+}
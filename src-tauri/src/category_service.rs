@@ -1,52 +1,421 @@
-use crate::models;
-use rusqlite::{named_params, Connection};
+use crate::journal_service::{Change, Entity};
+use crate::models::{self, CategoryKind, CategoryOutcome};
+use crate::shared_service::to_array;
+use rusqlite::{named_params, Connection, OptionalExtension, Row};
+
+// Colors for categories without a chosen color, picked by id so that a category keeps its color
+const PALETTE: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f",
+    "#edc948", "#b07aa1", "#ff9da7", "#9c755f", "#bab0ac",
+];
+
+// Columns read by category_from_row, the categories table is expected to be aliased as c
+pub const CATEGORY_COLUMNS: &str =
+    "c.id, c.label, c.color, c.icon, c.kind, c.sort_order, c.archived, c.display_label";
+
+pub fn category_color(id: i32, color: Option<String>) -> String {
+    color.unwrap_or_else(|| PALETTE[id.rem_euclid(PALETTE.len() as i32) as usize].to_string())
+}
+
+fn kind_from_str(kind: &str) -> CategoryKind {
+    match kind {
+        "INCOME" => CategoryKind::INCOME,
+        "EXPENSE" => CategoryKind::EXPENSE,
+        _ => CategoryKind::BOTH,
+    }
+}
+
+fn kind_to_str(kind: CategoryKind) -> &'static str {
+    match kind {
+        CategoryKind::INCOME => "INCOME",
+        CategoryKind::EXPENSE => "EXPENSE",
+        CategoryKind::BOTH => "BOTH",
+    }
+}
 
-pub fn insert_category(db: &Connection, label: &str) -> Result<(), rusqlite::Error> {
-    db.execute(
-        "INSERT OR IGNORE INTO categories (label) VALUES (:label);",
+// Reads the CATEGORY_COLUMNS starting from the given column
+pub fn category_from_row(row: &Row, first: usize) -> Result<models::Category, rusqlite::Error> {
+    let id = row.get(first)?;
+    Ok(models::Category {
+        id,
+        label: row.get(first + 1)?,
+        color: category_color(id, row.get(first + 2)?),
+        icon: row.get(first + 3)?,
+        kind: kind_from_str(&row.get::<_, String>(first + 4)?),
+        sort_order: row.get(first + 5)?,
+        archived: row.get(first + 6)?,
+        display_label: row
+            .get::<_, Option<String>>(first + 7)?
+            .unwrap_or(row.get(first + 1)?),
+    })
+}
+
+// expected color is of format #rrggbb
+fn validate_color(color: &str) -> Result<(), rusqlite::Error> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        return Err(rusqlite::Error::ToSqlConversionFailure(
+            format!("Invalid color: {:?}", color).into(),
+        ));
+    }
+    Ok(())
+}
+
+// Finds the category with the label regardless of casing, also from the trash
+fn find_by_label(db: &Connection, label: &str) -> Result<Option<(models::Category, bool)>, rusqlite::Error> {
+    db.query_row(
+        &format!(
+            "SELECT {}, c.deleted_at IS NOT NULL FROM categories c WHERE c.label = (:label);",
+            CATEGORY_COLUMNS
+        ),
         named_params! {
             ":label": label.to_lowercase().trim()
         },
-    )?;
-    Ok(())
+        |row| Ok((category_from_row(row, 0)?, row.get(8)?)),
+    )
+    .optional()
+}
+
+pub fn get_category(db: &Connection, id: i32) -> Result<models::Category, rusqlite::Error> {
+    db.query_row(
+        &format!(
+            "SELECT {} FROM categories c WHERE c.id = (:id) AND c.deleted_at IS NULL;",
+            CATEGORY_COLUMNS
+        ),
+        named_params! {
+            ":id": id
+        },
+        |row| category_from_row(row, 0),
+    )
+}
+
+pub fn insert_category(db: &Connection, label: &str) -> Result<CategoryOutcome, rusqlite::Error> {
+    // the label is unique, a category with the same label in the trash is restored instead
+    match find_by_label(db, label)? {
+        Some((category, true)) => {
+            // the restored category takes the casing of the new label
+            return crate::shared_service::in_savepoint(db, || {
+                let before = crate::journal_service::snapshot(db, Entity::Category, category.id)?;
+                db.execute(
+                    "UPDATE categories SET deleted_at = NULL, display_label = (:display_label) WHERE id = (:id);",
+                    named_params! {
+                        ":id": category.id,
+                        ":display_label": label.trim(),
+                    },
+                )?;
+                crate::journal_service::record(
+                    db,
+                    "restore_category",
+                    vec![Change {
+                        entity: Entity::Category,
+                        entity_id: category.id,
+                        before,
+                        after: crate::journal_service::snapshot(db, Entity::Category, category.id)?,
+                    }],
+                )?;
+                Ok(CategoryOutcome::RESTORED(get_category(db, category.id)?))
+            });
+        }
+        Some((category, false)) => return Ok(CategoryOutcome::ALREADY_EXISTS(category)),
+        None => {}
+    }
+
+    crate::shared_service::in_savepoint(db, || {
+        db.execute(
+            "
+            INSERT INTO categories (label, display_label, sort_order)
+            VALUES (:label, :display_label, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM categories));
+            ",
+            named_params! {
+                ":label": label.to_lowercase().trim(),
+                ":display_label": label.trim(),
+            },
+        )?;
+
+        let id = db.last_insert_rowid() as i32;
+        crate::journal_service::record(
+            db,
+            "insert_category",
+            vec![Change {
+                entity: Entity::Category,
+                entity_id: id,
+                before: None,
+                after: crate::journal_service::snapshot(db, Entity::Category, id)?,
+            }],
+        )?;
+        Ok(CategoryOutcome::CREATED(get_category(db, id)?))
+    })
 }
 
+// Changing only the casing of the label keeps the category, a label used by another category
+// is reported as a conflict instead of renaming
 pub fn update_category_label(
     db: &Connection,
     id: i32,
     label: &str,
-) -> Result<(), rusqlite::Error> {
-    db.execute(
-        "UPDATE categories SET label = (:label) WHERE id = (:id);",
-        named_params! {
-            ":label": label.to_lowercase().trim(),
-            ":id": id,
-        },
-    )?;
-    Ok(())
+) -> Result<CategoryOutcome, rusqlite::Error> {
+    // a category in the trash keeps its label until it is purged
+    match find_by_label(db, label)? {
+        Some((category, false)) if category.id != id => {
+            return Ok(CategoryOutcome::CONFLICT(category));
+        }
+        Some((category, true)) if category.id != id => {
+            return Ok(CategoryOutcome::CONFLICT_IN_TRASH(category));
+        }
+        _ => {}
+    }
+
+    crate::shared_service::in_savepoint(db, || {
+        let before = crate::journal_service::snapshot(db, Entity::Category, id)?;
+        // fails when the category does not exist
+        get_category(db, id)?;
+
+        db.execute(
+            "UPDATE categories SET label = (:label), display_label = (:display_label) WHERE id = (:id);",
+            named_params! {
+                ":label": label.to_lowercase().trim(),
+                ":display_label": label.trim(),
+                ":id": id,
+            },
+        )?;
+
+        crate::journal_service::record(
+            db,
+            "update_category_label",
+            vec![Change {
+                entity: Entity::Category,
+                entity_id: id,
+                before,
+                after: crate::journal_service::snapshot(db, Entity::Category, id)?,
+            }],
+        )?;
+        Ok(CategoryOutcome::UPDATED(get_category(db, id)?))
+    })
 }
 
+// The category is moved to the trash, its links to transactions are kept so that it can be
+// restored, see trash_service
 pub fn delete_category(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
-    db.execute(
-        "DELETE FROM categories WHERE id = (:id);",
-        named_params! {
-            ":id": id,
-        },
-    )?;
+    crate::trash_service::move_to_trash(db, Entity::Category, id, "delete_category")?;
     Ok(())
 }
 
+// Transactions of the source categories are linked to the target instead and the sources are
+// moved to the trash rather than deleted, so the merge can be undone. Their labels stay taken
+// until the trash is purged. There are no budgets or rules referring to categories to move.
+// Returns the number of transactions that were re-linked.
+pub fn merge_categories(
+    db: &Connection,
+    source_ids: Vec<i32>,
+    target_id: i32,
+) -> Result<usize, rusqlite::Error> {
+    db.query_row(
+        "SELECT id FROM categories WHERE id = (:id) AND deleted_at IS NULL;",
+        named_params! { ":id": target_id },
+        |row| row.get::<_, i32>(0),
+    )?;
+
+    crate::shared_service::in_savepoint(db, || {
+        let mut stmt = db.prepare(
+            "
+            SELECT id FROM categories
+            WHERE id IN rarray(:ids) AND id != (:target_id) AND deleted_at IS NULL;
+            ",
+        )?;
+        let sources = stmt
+            .query_map(
+                named_params! { ":ids": to_array(&source_ids), ":target_id": target_id },
+                |row| row.get(0),
+            )?
+            .collect::<Result<Vec<i32>, rusqlite::Error>>()?;
+        if sources.is_empty() {
+            return Ok(0);
+        }
+
+        let mut stmt = db.prepare(
+            "SELECT DISTINCT transaction_id FROM transaction_categories WHERE category_id IN rarray(:ids);",
+        )?;
+        let transactions = stmt
+            .query_map(named_params! { ":ids": to_array(&sources) }, |row| row.get(0))?
+            .collect::<Result<Vec<i32>, rusqlite::Error>>()?;
+
+        let mut changes = Vec::new();
+        for (entity, ids) in [(Entity::Transaction, &transactions), (Entity::Category, &sources)] {
+            for id in ids {
+                changes.push(Change {
+                    entity,
+                    entity_id: *id,
+                    before: crate::journal_service::snapshot(db, entity, *id)?,
+                    after: None,
+                });
+            }
+        }
+
+        // a transaction may already be linked to the target and can only be linked once
+        db.execute(
+            "
+            INSERT OR IGNORE INTO transaction_categories (transaction_id, category_id)
+            SELECT transaction_id, (:target_id) FROM transaction_categories
+            WHERE category_id IN rarray(:ids);
+            ",
+            named_params! { ":ids": to_array(&sources), ":target_id": target_id },
+        )?;
+        db.execute(
+            "DELETE FROM transaction_categories WHERE category_id IN rarray(:ids);",
+            named_params! { ":ids": to_array(&sources) },
+        )?;
+        db.execute(
+            "UPDATE categories SET deleted_at = CURRENT_TIMESTAMP WHERE id IN rarray(:ids);",
+            named_params! { ":ids": to_array(&sources) },
+        )?;
+        db.execute(
+            "UPDATE transactions SET updated_at = CURRENT_TIMESTAMP WHERE id IN rarray(:ids);",
+            named_params! { ":ids": to_array(&transactions) },
+        )?;
+
+        for change in changes.iter_mut() {
+            change.after = crate::journal_service::snapshot(db, change.entity, change.entity_id)?;
+        }
+        crate::journal_service::record(db, "merge_categories", changes)?;
+        Ok(transactions.len())
+    })
+}
+
+// Color, icon, kind and archived flag of the category, a None color goes back to the palette
+pub fn update_category_metadata(
+    db: &Connection,
+    id: i32,
+    color: Option<&str>,
+    icon: Option<&str>,
+    kind: CategoryKind,
+    archived: bool,
+) -> Result<(), rusqlite::Error> {
+    if let Some(color) = color {
+        validate_color(color)?;
+    }
+
+    crate::shared_service::in_savepoint(db, || {
+        let before = crate::journal_service::snapshot(db, Entity::Category, id)?;
+        if before.is_none() {
+            return Ok(());
+        }
+
+        db.execute(
+            "
+            UPDATE categories
+            SET color = (:color), icon = (:icon), kind = (:kind), archived = (:archived)
+            WHERE id = (:id);
+            ",
+            named_params! {
+                ":color": color.map(|color| color.to_lowercase()),
+                ":icon": icon,
+                ":kind": kind_to_str(kind),
+                ":archived": archived,
+                ":id": id,
+            },
+        )?;
+
+        crate::journal_service::record(
+            db,
+            "update_category_metadata",
+            vec![Change {
+                entity: Entity::Category,
+                entity_id: id,
+                before,
+                after: crate::journal_service::snapshot(db, Entity::Category, id)?,
+            }],
+        )
+    })
+}
+
+// Categories are listed in the given order, categories missing from the list keep their place
+pub fn set_category_order(db: &Connection, ids: Vec<i32>) -> Result<(), rusqlite::Error> {
+    crate::shared_service::in_savepoint(db, || {
+        let mut changes = Vec::new();
+        for (index, id) in ids.into_iter().enumerate() {
+            let before = crate::journal_service::snapshot(db, Entity::Category, id)?;
+            if before.is_none() {
+                continue;
+            }
+            db.execute(
+                "UPDATE categories SET sort_order = (:sort_order) WHERE id = (:id);",
+                named_params! {
+                    ":sort_order": index as i32 + 1,
+                    ":id": id,
+                },
+            )?;
+            changes.push(Change {
+                entity: Entity::Category,
+                entity_id: id,
+                before,
+                after: crate::journal_service::snapshot(db, Entity::Category, id)?,
+            });
+        }
+        crate::journal_service::record(db, "set_category_order", changes)
+    })
+}
+
+// Categories for picking, archived categories are left out
 pub fn get_categories(db: &Connection) -> Result<Vec<models::Category>, rusqlite::Error> {
-    let mut stmt = db.prepare("SELECT id, label FROM categories;")?;
-    let mut rows = stmt.query([])?;
+    query_categories(db, false)
+}
+
+pub fn query_categories(
+    db: &Connection,
+    include_archived: bool,
+) -> Result<Vec<models::Category>, rusqlite::Error> {
+    let mut stmt = db.prepare(&format!(
+        "
+        SELECT {} FROM categories c
+        WHERE c.deleted_at IS NULL AND ((:include_archived) OR c.archived = 0)
+        ORDER BY c.sort_order, c.id;
+        ",
+        CATEGORY_COLUMNS
+    ))?;
+    let mut rows = stmt.query(named_params! { ":include_archived": include_archived })?;
     let mut categories = Vec::new();
     while let Some(row) = rows.next()? {
-        categories.push(models::Category {
-            id: row.get(0)?,
+        categories.push(category_from_row(row, 0)?);
+    }
+    Ok(categories)
+}
+
+// How much every category is used by transactions, archived categories are included.
+// Transactions are the only references to a category: there are no budgets, and payee
+// patterns assign payees rather than categories, so those are not part of the usage.
+pub fn get_category_usage(db: &Connection) -> Result<Vec<models::CategoryUsage>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "
+        SELECT
+        c.id,
+        c.label,
+        COUNT(t.id),
+        COALESCE(SUM(t.value), 0),
+        MIN(date(t.date_created)),
+        MAX(date(t.date_created))
+        FROM categories c
+        LEFT JOIN transaction_categories tc ON tc.category_id = c.id
+        LEFT JOIN transactions t ON t.id = tc.transaction_id AND t.deleted_at IS NULL
+        WHERE c.deleted_at IS NULL
+        GROUP BY c.id
+        ORDER BY c.sort_order, c.id;
+        ",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut usage = Vec::new();
+    while let Some(row) = rows.next()? {
+        usage.push(models::CategoryUsage {
+            category_id: row.get(0)?,
             label: row.get(1)?,
+            transaction_count: row.get(2)?,
+            total: row.get(3)?,
+            first_used: row.get(4)?,
+            last_used: row.get(5)?,
         });
     }
-    Ok(categories)
+    Ok(usage)
 }
 
 #[cfg(test)]
@@ -189,3 +558,170 @@ fn should_be_able_to_change_label() -> Result<(), rusqlite::Error> {
 
     Ok(())
 }
+
+#[test]
+fn merge_should_relink_transactions_and_trash_sources() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_category(&conn, "groceries")?;
+    insert_category(&conn, "grocery")?;
+    insert_category(&conn, "food")?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "a", None, "2023-11-01", None, vec![2])?;
+    // already linked to the target
+    crate::transaction_service::insert_transaction(&conn, -20.0, "b", None, "2023-11-02", None, vec![1, 2, 3])?;
+
+    assert_eq!(merge_categories(&conn, vec![2, 3, 1], 1)?, 2);
+
+    let categories = get_categories(&conn)?;
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0].label, "groceries");
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![1], vec![], vec![])?;
+    assert_eq!(page.transactions.len(), 2);
+    assert!(page.transactions.iter().all(|t| t.categories.len() == 1));
+
+    crate::journal_service::undo(&conn)?;
+    assert_eq!(get_categories(&conn)?.len(), 3);
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "b", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions[0].categories.len(), 3);
+
+    assert!(merge_categories(&conn, vec![2], 9).is_err());
+    Ok(())
+}
+
+#[test]
+fn merged_source_label_should_be_taken_until_purged() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_category(&conn, "groceries")?;
+    insert_category(&conn, "grocery")?;
+    insert_category(&conn, "food")?;
+    merge_categories(&conn, vec![2], 1)?;
+
+    match update_category_label(&conn, 3, "grocery")? {
+        CategoryOutcome::CONFLICT_IN_TRASH(category) => assert_eq!(category.id, 2),
+        outcome => panic!("Expected a conflict with the trash, got {:?}", outcome),
+    }
+    // inserting the label brings back the emptied source instead of a new category
+    match insert_category(&conn, "grocery")? {
+        CategoryOutcome::RESTORED(category) => assert_eq!(category.id, 2),
+        outcome => panic!("Expected the source to be restored, got {:?}", outcome),
+    }
+
+    merge_categories(&conn, vec![2], 1)?;
+    crate::trash_service::purge_trash(&conn, 0)?;
+    match insert_category(&conn, "grocery")? {
+        CategoryOutcome::CREATED(category) => assert_ne!(category.id, 2),
+        outcome => panic!("Expected a new category, got {:?}", outcome),
+    }
+    Ok(())
+}
+
+#[test]
+fn metadata_should_be_returned_and_archived_hidden() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_category(&conn, "food")?;
+    insert_category(&conn, "rent")?;
+    insert_category(&conn, "salary")?;
+
+    let categories = get_categories(&conn)?;
+    assert_eq!(categories[0].kind, CategoryKind::BOTH);
+    // colors are stable without being chosen
+    assert_eq!(categories[0].color, category_color(1, None));
+
+    assert!(update_category_metadata(&conn, 1, Some("red"), None, CategoryKind::EXPENSE, false).is_err());
+    update_category_metadata(&conn, 1, Some("#FF0000"), Some("cart"), CategoryKind::EXPENSE, false)?;
+    update_category_metadata(&conn, 2, None, None, CategoryKind::EXPENSE, true)?;
+    set_category_order(&conn, vec![3, 1])?;
+
+    let categories = get_categories(&conn)?;
+    let labels: Vec<&str> = categories.iter().map(|c| c.label.as_str()).collect();
+    assert_eq!(labels, vec!["salary", "food"]);
+    assert_eq!(categories[1].color, "#ff0000");
+    assert_eq!(categories[1].icon.as_deref(), Some("cart"));
+
+    let categories = query_categories(&conn, true)?;
+    assert_eq!(categories.len(), 3);
+    assert!(categories.iter().any(|c| c.archived));
+    Ok(())
+}
+
+#[test]
+fn usage_should_count_transactions_of_category() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_category(&conn, "food")?;
+    insert_category(&conn, "rent")?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "a", None, "2023-11-03 12:00", None, vec![1])?;
+    crate::transaction_service::insert_transaction(&conn, -20.0, "b", None, "2023-10-01", None, vec![1])?;
+    crate::transaction_service::insert_transaction(&conn, -30.0, "c", None, "2023-12-01", None, vec![1])?;
+    crate::transaction_service::delete_transaction(&conn, 3)?;
+
+    let usage = get_category_usage(&conn)?;
+    assert_eq!(usage.len(), 2);
+    assert_eq!(usage[0].transaction_count, 2);
+    assert_eq!(usage[0].total, -30.0);
+    assert_eq!(usage[0].first_used.as_deref(), Some("2023-10-01"));
+    assert_eq!(usage[0].last_used.as_deref(), Some("2023-11-03"));
+    assert_eq!(usage[1].transaction_count, 0);
+    assert_eq!(usage[1].first_used, None);
+    Ok(())
+}
+
+#[test]
+fn insert_should_report_existing_category() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    let id = match insert_category(&conn, " Groceries ")? {
+        CategoryOutcome::CREATED(category) => {
+            assert_eq!(category.label, "groceries");
+            assert_eq!(category.display_label, "Groceries");
+            category.id
+        }
+        outcome => panic!("Expected a new category, got {:?}", outcome),
+    };
+
+    match insert_category(&conn, "GROCERIES")? {
+        CategoryOutcome::ALREADY_EXISTS(category) => assert_eq!(category.id, id),
+        outcome => panic!("Expected the existing category, got {:?}", outcome),
+    }
+
+    delete_category(&conn, id)?;
+    match insert_category(&conn, "GROCERIES")? {
+        CategoryOutcome::RESTORED(category) => {
+            assert_eq!(category.id, id);
+            assert_eq!(category.display_label, "GROCERIES");
+        }
+        outcome => panic!("Expected the restored category, got {:?}", outcome),
+    }
+
+    crate::journal_service::undo(&conn)?;
+    crate::journal_service::redo(&conn)?;
+    assert_eq!(get_category(&conn, id)?.display_label, "GROCERIES");
+    Ok(())
+}
+
+#[test]
+fn update_should_report_label_conflict() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_category(&conn, "food")?;
+    insert_category(&conn, "rent")?;
+
+    match update_category_label(&conn, 2, "Food")? {
+        CategoryOutcome::CONFLICT(category) => assert_eq!(category.id, 1),
+        outcome => panic!("Expected a conflict, got {:?}", outcome),
+    }
+    assert_eq!(get_category(&conn, 2)?.label, "rent");
+
+    // changing the casing of its own label is not a conflict
+    match update_category_label(&conn, 1, "FOOD")? {
+        CategoryOutcome::UPDATED(category) => assert_eq!(category.display_label, "FOOD"),
+        outcome => panic!("Expected an update, got {:?}", outcome),
+    }
+
+    delete_category(&conn, 1)?;
+    match update_category_label(&conn, 2, "food")? {
+        CategoryOutcome::CONFLICT_IN_TRASH(category) => assert_eq!(category.id, 1),
+        outcome => panic!("Expected a conflict with the trash, got {:?}", outcome),
+    }
+    assert_eq!(get_category(&conn, 2)?.label, "rent");
+
+    assert!(update_category_label(&conn, 9, "other").is_err());
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::models::DatabaseKind;
+use rusqlite::ErrorCode;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const RECENT_FILES_FILE: &str = "recent_files";
+const RECENT_FILES_LIMIT: usize = 10;
+
+// Tables every finance-app database has since the first version
+const REQUIRED_TABLES: &[&str] = &["categories", "transactions", "transaction_categories"];
+
+// Without a passphrase an encrypted database cannot be inspected, a file that does not exist
+// yet is treated as an empty database and a file that is no database at all as OTHER
+pub fn get_database_kind(
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<DatabaseKind, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(DatabaseKind::EMPTY);
+    }
+    if passphrase.is_none() && crate::encryption_service::is_encrypted(path)? {
+        return Ok(DatabaseKind::ENCRYPTED);
+    }
+
+    let db = match crate::encryption_service::open_database(path, passphrase) {
+        Ok(db) => db,
+        Err(rusqlite::Error::SqliteFailure(error, _))
+            if passphrase.is_none() && error.code == ErrorCode::NotADatabase =>
+        {
+            return Ok(DatabaseKind::OTHER);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut stmt = db.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%';",
+    )?;
+    let tables = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+
+    if tables.is_empty() {
+        Ok(DatabaseKind::EMPTY)
+    } else if REQUIRED_TABLES
+        .iter()
+        .all(|table| tables.iter().any(|name| name == table))
+    {
+        Ok(DatabaseKind::FINANCE_APP)
+    } else {
+        Ok(DatabaseKind::OTHER)
+    }
+}
+
+// Most recently opened first
+pub fn get_recent_files(app_dir: &Path) -> Vec<String> {
+    fs::read_to_string(app_dir.join(RECENT_FILES_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+pub fn add_recent_file(app_dir: &Path, path: &Path) -> Result<(), Box<dyn Error>> {
+    let path: PathBuf = fs::canonicalize(path)?;
+    let path = path.to_string_lossy().to_string();
+
+    let mut recent_files = get_recent_files(app_dir);
+    recent_files.retain(|file| *file != path);
+    recent_files.insert(0, path);
+    recent_files.truncate(RECENT_FILES_LIMIT);
+
+    fs::create_dir_all(app_dir)?;
+    fs::write(app_dir.join(RECENT_FILES_FILE), recent_files.join("\n"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+use crate::shared_service::TestDir;
+
+#[test]
+fn database_kind_should_be_detected() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("database_kind_should_be_detected");
+
+    let path = dir.join("new.sqlite");
+    assert_eq!(get_database_kind(&path, None)?, DatabaseKind::EMPTY);
+    let db = crate::encryption_service::open_database(&path, None)?;
+    crate::migration_service::init_tables(&db)?;
+    assert_eq!(get_database_kind(&path, None)?, DatabaseKind::FINANCE_APP);
+
+    let path = dir.join("other.sqlite");
+    let db = crate::encryption_service::open_database(&path, None)?;
+    db.execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY);")?;
+    assert_eq!(get_database_kind(&path, None)?, DatabaseKind::OTHER);
+
+    let path = dir.join("statement.pdf");
+    fs::write(&path, b"%PDF-1.4 not a database")?;
+    assert_eq!(get_database_kind(&path, None)?, DatabaseKind::OTHER);
+
+    let path = dir.join("encrypted.sqlite");
+    let db = crate::encryption_service::open_database(&path, Some("secret"))?;
+    crate::migration_service::init_tables(&db)?;
+    assert_eq!(get_database_kind(&path, None)?, DatabaseKind::ENCRYPTED);
+    assert_eq!(get_database_kind(&path, Some("secret"))?, DatabaseKind::FINANCE_APP);
+    assert!(get_database_kind(&path, Some("wrong")).is_err());
+    Ok(())
+}
+
+#[test]
+fn recent_files_should_be_unique_and_limited() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("recent_files_should_be_unique_and_limited");
+    for i in 0..12 {
+        let path = dir.join(format!("{}.sqlite", i));
+        fs::write(&path, b"")?;
+        add_recent_file(&dir, &path)?;
+    }
+    add_recent_file(&dir, &dir.join("5.sqlite"))?;
+
+    let recent_files = get_recent_files(&dir);
+    assert_eq!(recent_files.len(), RECENT_FILES_LIMIT);
+    assert!(recent_files[0].ends_with("5.sqlite"));
+    assert!(recent_files[1].ends_with("11.sqlite"));
+    assert_eq!(recent_files.iter().filter(|file| file.ends_with("5.sqlite")).count(), 1);
+    Ok(())
+}
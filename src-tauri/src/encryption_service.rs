@@ -0,0 +1,166 @@
+use rusqlite::{named_params, Connection, DatabaseName, ErrorCode};
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+// The database is encrypted with SQLCipher, an empty passphrase means no encryption
+fn key(passphrase: Option<&str>) -> &str {
+    passphrase.unwrap_or_default()
+}
+
+// Opens the database with the passphrase and checks that it can be read, a wrong passphrase
+// fails with a NotADatabase error
+pub fn open_database(path: &Path, passphrase: Option<&str>) -> Result<Connection, rusqlite::Error> {
+    let db = Connection::open(path)?;
+    if !key(passphrase).is_empty() {
+        db.pragma_update(None, "key", key(passphrase))?;
+    }
+    db.query_row("SELECT COUNT(*) FROM sqlite_master;", [], |row| row.get::<_, i32>(0))?;
+    Ok(db)
+}
+
+// Every plain SQLite database starts with this header, SQLCipher stores a random salt in its place
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+// Smallest page size, the file of an encrypted database consists of whole pages
+const MIN_PAGE_SIZE: u64 = 512;
+
+// A missing database is created unencrypted on first start. Files which are not databases at
+// all are not encrypted either, errors other than an unreadable database are returned as is.
+pub fn is_encrypted(path: &Path) -> Result<bool, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let mut header = [0; 16];
+    let mut file = fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    if size < MIN_PAGE_SIZE || size % MIN_PAGE_SIZE != 0 {
+        return Ok(false);
+    }
+    file.read_exact(&mut header)?;
+    if &header == SQLITE_HEADER {
+        return Ok(false);
+    }
+
+    match open_database(path, None) {
+        Ok(_) => Ok(false),
+        Err(rusqlite::Error::SqliteFailure(error, _)) if error.code == ErrorCode::NotADatabase => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Writes a copy of the database encrypted with the passphrase, or a plain copy without one
+pub fn export_database(
+    db: &Connection,
+    target: &Path,
+    passphrase: Option<&str>,
+) -> Result<(), rusqlite::Error> {
+    let version: i32 = db.query_row("PRAGMA user_version;", [], |row| row.get(0))?;
+
+    db.execute(
+        "ATTACH DATABASE (:path) AS export KEY (:key);",
+        named_params! {
+            ":path": target.to_string_lossy(),
+            ":key": key(passphrase),
+        },
+    )?;
+    let exported = db
+        .query_row("SELECT sqlcipher_export('export');", [], |_| Ok(()))
+        .and_then(|_| db.pragma_update(Some(DatabaseName::Attached("export")), "user_version", version));
+    db.execute_batch("DETACH DATABASE export;")?;
+    exported
+}
+
+// Replaces the database file with a copy using the new passphrase, this is also how an existing
+// plain database is encrypted. The connection is kept when the copy fails, after that the
+// database stays closed until it has been reopened with the new passphrase.
+pub fn change_passphrase(
+    db: &mut Option<Connection>,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let connection = db.as_ref().ok_or("No database is open")?;
+    let target = path.with_extension("sqlite.export");
+    if target.exists() {
+        fs::remove_file(&target)?;
+    }
+    export_database(connection, &target, passphrase)?;
+
+    if let Some(connection) = db.take() {
+        connection.close().map_err(|(_, e)| e)?;
+    }
+    fs::rename(&target, path)?;
+
+    let connection = open_database(path, passphrase)?;
+    crate::migration_service::init_tables(&connection)?;
+    *db = Some(connection);
+    Ok(())
+}
+
+#[cfg(test)]
+use crate::shared_service::TestDir;
+
+#[test]
+fn plain_database_should_be_encrypted() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("plain_database_should_be_encrypted");
+    let path = dir.join("finance.sqlite");
+    let db = open_database(&path, None)?;
+    crate::migration_service::init_tables(&db)?;
+    crate::category_service::insert_category(&db, "food")?;
+    assert!(!is_encrypted(&path)?);
+
+    let mut db = Some(db);
+    change_passphrase(&mut db, &path, Some("secret"))?;
+    assert_eq!(crate::category_service::get_categories(db.as_ref().unwrap())?.len(), 1);
+    drop(db);
+
+    assert!(is_encrypted(&path)?);
+    assert!(open_database(&path, Some("wrong")).is_err());
+
+    let db = open_database(&path, Some("secret"))?;
+    crate::migration_service::init_tables(&db)?;
+    assert_eq!(crate::category_service::get_categories(&db)?[0].label, "food");
+    Ok(())
+}
+
+#[test]
+fn passphrase_should_be_changed_and_removed() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("passphrase_should_be_changed_and_removed");
+    let path = dir.join("finance.sqlite");
+    let db = open_database(&path, Some("first"))?;
+    crate::migration_service::init_tables(&db)?;
+    crate::category_service::insert_category(&db, "food")?;
+
+    let mut db = Some(db);
+    change_passphrase(&mut db, &path, Some("second"))?;
+    drop(db);
+    assert!(open_database(&path, Some("first")).is_err());
+
+    let mut db = Some(open_database(&path, Some("second"))?);
+    change_passphrase(&mut db, &path, None)?;
+    drop(db);
+    assert!(!is_encrypted(&path)?);
+
+    // the schema version is kept so no migration runs twice
+    let db = open_database(&path, None)?;
+    let version: i32 = db.query_row("PRAGMA user_version;", [], |row| row.get(0))?;
+    assert!(version > 0);
+    crate::migration_service::init_tables(&db)?;
+    assert_eq!(crate::category_service::get_categories(&db)?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn other_files_should_not_be_reported_as_encrypted() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("other_files_should_not_be_reported_as_encrypted");
+    let path = dir.join("finance.sqlite");
+    fs::write(&path, b"%PDF-1.4 not a database")?;
+    assert!(!is_encrypted(&path)?);
+
+    // random content of whole pages cannot be told apart from an encrypted database
+    fs::write(&path, [7; 4096])?;
+    assert!(is_encrypted(&path)?);
+    Ok(())
+}
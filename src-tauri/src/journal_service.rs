@@ -0,0 +1,494 @@
+use crate::shared_service::invalid_input;
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{named_params, params_from_iter, Connection};
+use serde_json::{json, Map};
+
+// Number of operations that can be undone, older operations are dropped from the journal
+const JOURNAL_SIZE: i64 = 100;
+
+// Entities of which changes can be undone: transactions with their categories and tags, and
+// categories with their transactions. Payees and their patterns, creating or deleting tags,
+// attachments and accounts are not journaled, changes to those can't be undone.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Entity {
+    Transaction,
+    Category,
+}
+
+impl Entity {
+    fn name(&self) -> &'static str {
+        match self {
+            Entity::Transaction => "transaction",
+            Entity::Category => "category",
+        }
+    }
+
+    pub fn table(&self) -> &'static str {
+        match self {
+            Entity::Transaction => "transactions",
+            Entity::Category => "categories",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Entity, rusqlite::Error> {
+        match name {
+            "transaction" => Ok(Entity::Transaction),
+            "category" => Ok(Entity::Category),
+            _ => Err(invalid_input(&format!("Unknown journal entity: {:?}", name))),
+        }
+    }
+}
+
+// A change of a single entity, a missing snapshot means the entity did not exist
+#[derive(Debug)]
+pub struct Change {
+    pub entity: Entity,
+    pub entity_id: i32,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+fn to_json(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => serde_json::Value::Null,
+        ValueRef::Integer(i) => json!(i),
+        ValueRef::Real(f) => json!(f),
+        ValueRef::Text(t) => json!(String::from_utf8_lossy(t)),
+    }
+}
+
+fn from_json(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => Value::Text(s.to_string()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+fn snapshot_row(
+    db: &Connection,
+    table: &str,
+    id: i32,
+) -> Result<Option<Map<String, serde_json::Value>>, rusqlite::Error> {
+    let mut stmt = db.prepare(&format!("SELECT * FROM {} WHERE id = (:id);", table))?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows = stmt.query(named_params! { ":id": id })?;
+
+    match rows.next()? {
+        Some(row) => {
+            let mut snapshot = Map::new();
+            for (index, column) in columns.into_iter().enumerate() {
+                snapshot.insert(column, to_json(row.get_ref(index)?));
+            }
+            Ok(Some(snapshot))
+        }
+        None => Ok(None),
+    }
+}
+
+fn query_ids(db: &Connection, stmt: &str, id: i32) -> Result<Vec<i32>, rusqlite::Error> {
+    let mut stmt = db.prepare(stmt)?;
+    let ids = stmt
+        .query_map(named_params! { ":id": id }, |row| row.get(0))?
+        .collect::<Result<Vec<i32>, rusqlite::Error>>()?;
+    Ok(ids)
+}
+
+// Current state of the entity including the links to other entities
+pub fn snapshot(
+    db: &Connection,
+    entity: Entity,
+    id: i32,
+) -> Result<Option<serde_json::Value>, rusqlite::Error> {
+    let row = match snapshot_row(db, entity.table(), id)? {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+
+    let snapshot = match entity {
+        Entity::Transaction => json!({
+            "row": row,
+            "category_ids": query_ids(
+                db,
+                "SELECT category_id FROM transaction_categories WHERE transaction_id = (:id);",
+                id,
+            )?,
+            "tag_ids": query_ids(
+                db,
+                "SELECT tag_id FROM transaction_tags WHERE transaction_id = (:id);",
+                id,
+            )?,
+        }),
+        Entity::Category => json!({
+            "row": row,
+            "transaction_ids": query_ids(
+                db,
+                "SELECT transaction_id FROM transaction_categories WHERE category_id = (:id);",
+                id,
+            )?,
+        }),
+    };
+    Ok(Some(snapshot))
+}
+
+fn restore_row(
+    db: &Connection,
+    entity: Entity,
+    row: &Map<String, serde_json::Value>,
+) -> Result<(), rusqlite::Error> {
+    let table = entity.table();
+    // snapshots taken by older versions may contain dates in another format
+    let mut row = row.clone();
+    if let (Entity::Transaction, Some(serde_json::Value::String(date))) =
+        (entity, row.get("date_created"))
+    {
+        let date = crate::migration_service::normalize_date(date)
+            .ok_or_else(|| crate::shared_service::invalid_date(date))?;
+        row.insert("date_created".to_string(), json!(date));
+    }
+
+    // the schema may have changed since the snapshot was taken
+    let mut stmt = db.prepare(&format!("SELECT name FROM pragma_table_info('{}');", table))?;
+    let columns = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>, rusqlite::Error>>()?;
+
+    let entries: Vec<(&String, &serde_json::Value)> = row
+        .iter()
+        .filter(|(column, _)| columns.contains(column))
+        .collect();
+
+    let names: Vec<&str> = entries.iter().map(|(column, _)| column.as_str()).collect();
+    let placeholders: Vec<String> = (1..=entries.len()).map(|i| format!("?{}", i)).collect();
+    let updates: Vec<String> = names
+        .iter()
+        .filter(|column| **column != "id")
+        .map(|column| format!("{} = excluded.{}", column, column))
+        .collect();
+
+    db.execute(
+        &format!(
+            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT (id) DO UPDATE SET {};",
+            table,
+            names.join(", "),
+            placeholders.join(", "),
+            updates.join(", ")
+        ),
+        params_from_iter(entries.iter().map(|(_, value)| from_json(value))),
+    )?;
+    Ok(())
+}
+
+fn restore_links(
+    db: &Connection,
+    delete_stmt: &str,
+    insert_stmt: &str,
+    id: i32,
+    linked_ids: Option<&serde_json::Value>,
+) -> Result<(), rusqlite::Error> {
+    db.execute(delete_stmt, named_params! { ":id": id })?;
+
+    let linked_ids = linked_ids.and_then(|ids| ids.as_array()).cloned().unwrap_or_default();
+    for linked_id in linked_ids.iter().filter_map(|linked_id| linked_id.as_i64()) {
+        db.execute(
+            insert_stmt,
+            named_params! {
+                ":id": id,
+                ":linked_id": linked_id,
+            },
+        )?;
+    }
+    Ok(())
+}
+
+// Puts the entity back to the exact state of the snapshot including its timestamps, links to
+// entities that no longer exist are skipped
+pub fn restore(
+    db: &Connection,
+    entity: Entity,
+    id: i32,
+    snapshot: Option<&serde_json::Value>,
+) -> Result<(), rusqlite::Error> {
+    let snapshot = match snapshot {
+        Some(snapshot) => snapshot,
+        None => {
+            db.execute(
+                &format!("DELETE FROM {} WHERE id = (:id);", entity.table()),
+                named_params! { ":id": id },
+            )?;
+            return Ok(());
+        }
+    };
+
+    let row = snapshot["row"]
+        .as_object()
+        .ok_or(invalid_input("journal snapshot is missing its row"))?;
+    restore_row(db, entity, row)?;
+
+    match entity {
+        Entity::Transaction => {
+            restore_links(
+                db,
+                "DELETE FROM transaction_categories WHERE transaction_id = (:id);",
+                "
+                INSERT OR IGNORE INTO transaction_categories (transaction_id, category_id)
+                SELECT (:id), id FROM categories WHERE id = (:linked_id);
+                ",
+                id,
+                snapshot.get("category_ids"),
+            )?;
+            restore_links(
+                db,
+                "DELETE FROM transaction_tags WHERE transaction_id = (:id);",
+                "
+                INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id)
+                SELECT (:id), id FROM tags WHERE id = (:linked_id);
+                ",
+                id,
+                snapshot.get("tag_ids"),
+            )?;
+        }
+        Entity::Category => {
+            restore_links(
+                db,
+                "DELETE FROM transaction_categories WHERE category_id = (:id);",
+                "
+                INSERT OR IGNORE INTO transaction_categories (transaction_id, category_id)
+                SELECT id, (:id) FROM transactions WHERE id = (:linked_id);
+                ",
+                id,
+                snapshot.get("transaction_ids"),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Records the changes as a single operation that can be undone,
+// recording a new operation discards the operations that could be redone
+pub fn record(db: &Connection, label: &str, changes: Vec<Change>) -> Result<(), rusqlite::Error> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    db.execute("DELETE FROM journal_operations WHERE undone = 1;", [])?;
+    db.execute(
+        "INSERT INTO journal_operations (label) VALUES (:label);",
+        named_params! { ":label": label },
+    )?;
+    let operation_id = db.last_insert_rowid();
+
+    for change in changes {
+        db.execute(
+            "
+            INSERT INTO journal_changes (operation_id, entity, entity_id, before, after)
+            VALUES (:operation_id, :entity, :entity_id, :before, :after);
+            ",
+            named_params! {
+                ":operation_id": operation_id,
+                ":entity": change.entity.name(),
+                ":entity_id": change.entity_id,
+                ":before": change.before,
+                ":after": change.after,
+            },
+        )?;
+    }
+
+    db.execute(
+        "DELETE FROM journal_operations WHERE id <= (:id);",
+        named_params! { ":id": operation_id - JOURNAL_SIZE },
+    )?;
+    Ok(())
+}
+
+fn get_changes(db: &Connection, operation_id: i64) -> Result<Vec<Change>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "
+        SELECT entity, entity_id, before, after FROM journal_changes
+        WHERE operation_id = (:operation_id)
+        ORDER BY id;
+        ",
+    )?;
+    let mut rows = stmt.query(named_params! { ":operation_id": operation_id })?;
+
+    let mut changes = Vec::new();
+    while let Some(row) = rows.next()? {
+        let entity: String = row.get(0)?;
+        changes.push(Change {
+            entity: Entity::from_name(&entity)?,
+            entity_id: row.get(1)?,
+            before: row.get(2)?,
+            after: row.get(3)?,
+        });
+    }
+    Ok(changes)
+}
+
+fn next_operation(db: &Connection, stmt: &str) -> Result<Option<(i64, String)>, rusqlite::Error> {
+    let mut stmt = db.prepare(stmt)?;
+    let mut rows = stmt.query([])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?))),
+        None => Ok(None),
+    }
+}
+
+// Reverts the latest operation, returns the label of the reverted operation
+// or None when there is nothing to undo
+pub fn undo(db: &Connection) -> Result<Option<String>, rusqlite::Error> {
+    crate::shared_service::in_savepoint(db, || {
+        let (operation_id, label) = match next_operation(
+            db,
+            "SELECT id, label FROM journal_operations WHERE undone = 0 ORDER BY id DESC LIMIT 1;",
+        )? {
+            Some(operation) => operation,
+            None => return Ok(None),
+        };
+
+        for change in get_changes(db, operation_id)?.iter().rev() {
+            restore(db, change.entity, change.entity_id, change.before.as_ref())?;
+        }
+
+        db.execute(
+            "UPDATE journal_operations SET undone = 1 WHERE id = (:id);",
+            named_params! { ":id": operation_id },
+        )?;
+        Ok(Some(label))
+    })
+}
+
+// Applies the latest undone operation again, returns the label of the operation
+// or None when there is nothing to redo
+pub fn redo(db: &Connection) -> Result<Option<String>, rusqlite::Error> {
+    crate::shared_service::in_savepoint(db, || {
+        let (operation_id, label) = match next_operation(
+            db,
+            "SELECT id, label FROM journal_operations WHERE undone = 1 ORDER BY id ASC LIMIT 1;",
+        )? {
+            Some(operation) => operation,
+            None => return Ok(None),
+        };
+
+        for change in get_changes(db, operation_id)? {
+            restore(db, change.entity, change.entity_id, change.after.as_ref())?;
+        }
+
+        db.execute(
+            "UPDATE journal_operations SET undone = 0 WHERE id = (:id);",
+            named_params! { ":id": operation_id },
+        )?;
+        Ok(Some(label))
+    })
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn undo_should_return_none_when_empty() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    assert_eq!(undo(&conn)?, None);
+    assert_eq!(redo(&conn)?, None);
+    Ok(())
+}
+
+#[test]
+fn undo_should_restore_deleted_transaction_with_categories() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    let category = &crate::category_service::get_categories(&conn)?[0];
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test", Some("lunch"), "2023-11-01", None, vec![category.id])?;
+
+    crate::transaction_service::delete_transaction(&conn, 1)?;
+    assert_eq!(undo(&conn)?, Some("delete_transaction".to_string()));
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions.len(), 1);
+    let transaction = &page.transactions[0];
+    assert_eq!(transaction.id, 1);
+    assert_eq!(transaction.value, -10.0);
+    assert_eq!(transaction.description, Some("lunch".to_string()));
+    assert_eq!(transaction.categories.len(), 1);
+    assert_eq!(transaction.categories[0].id, category.id);
+
+    assert_eq!(redo(&conn)?, Some("delete_transaction".to_string()));
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert!(page.transactions.is_empty());
+    Ok(())
+}
+
+#[test]
+fn undo_should_restore_category_links_after_delete() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    let category = &crate::category_service::get_categories(&conn)?[0];
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test1", None, "2023-11-01", None, vec![category.id])?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test2", None, "2023-11-01", None, vec![category.id])?;
+
+    crate::category_service::delete_category(&conn, category.id)?;
+    assert_eq!(undo(&conn)?, Some("delete_category".to_string()));
+
+    let categories = crate::category_service::get_categories(&conn)?;
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0].id, category.id);
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![category.id], vec![], vec![])?;
+    assert_eq!(page.transactions.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn undo_should_revert_label_update_and_insert() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    let id = crate::category_service::get_categories(&conn)?[0].id;
+    crate::category_service::update_category_label(&conn, id, "groceries")?;
+
+    assert_eq!(undo(&conn)?, Some("update_category_label".to_string()));
+    assert_eq!(crate::category_service::get_categories(&conn)?[0].label, "food");
+
+    assert_eq!(undo(&conn)?, Some("insert_category".to_string()));
+    assert!(crate::category_service::get_categories(&conn)?.is_empty());
+
+    assert_eq!(redo(&conn)?, Some("insert_category".to_string()));
+    assert_eq!(redo(&conn)?, Some("update_category_label".to_string()));
+    assert_eq!(crate::category_service::get_categories(&conn)?[0].label, "groceries");
+    Ok(())
+}
+
+#[test]
+fn new_operation_should_discard_redo() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    undo(&conn)?;
+    crate::category_service::insert_category(&conn, "travel")?;
+
+    assert_eq!(redo(&conn)?, None);
+    let categories = crate::category_service::get_categories(&conn)?;
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0].label, "travel");
+    Ok(())
+}
+
+#[test]
+fn undo_should_normalize_dates_of_old_snapshots() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::delete_transaction(&conn, 1)?;
+
+    // as written before dates were normalized
+    conn.execute("UPDATE journal_changes SET before = json_set(before, '$.row.date_created', '01.11.2023');", [])?;
+    undo(&conn)?;
+    let date: String = conn.query_row("SELECT date_created FROM transactions WHERE id = 1;", [], |row| row.get(0))?;
+    assert_eq!(date, "2023-11-01");
+
+    redo(&conn)?;
+    conn.execute("UPDATE journal_changes SET before = json_set(before, '$.row.date_created', 'yesterday');", [])?;
+    assert!(undo(&conn).is_err());
+    assert!(crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?.transactions.is_empty());
+    Ok(())
+}
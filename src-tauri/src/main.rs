@@ -1,30 +1,103 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod account_service;
+mod asset_service;
+mod attachment_service;
+mod audit_service;
+mod bulk_service;
 mod category_service;
+mod database_service;
+mod encryption_service;
+mod journal_service;
 mod migration_service;
 mod models;
+mod payee_service;
+#[cfg(test)]
+mod performance_tests;
+mod profile_service;
+mod recurring_service;
 mod report_service;
+mod settings_service;
 mod shared_service;
 mod state;
+mod tag_service;
+mod trash_service;
 mod transaction_service;
 
 use rusqlite::Connection;
 use state::{AppState, ServiceAccess};
 use std::fs;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Manager, State};
 
+// Opens the database and brings its schema up to date, the database used by the commands
+// does not change until the returned connection replaces it
+#[cfg(not(tarpaulin_include))]
+fn prepare_database(path: &Path, passphrase: Option<&str>) -> Result<Connection, Box<dyn std::error::Error>> {
+    let db = encryption_service::open_database(path, passphrase)?;
+    migration_service::init_tables(&db)?;
+    let settings = settings_service::get_settings(&db)?;
+    trash_service::purge_trash(&db, settings.trash_retention_days)?;
+    attachment_service::prepare_attachments(&db, path)?;
+    Ok(db)
+}
+
+// Replaces the database used by the commands, an encrypted database opened without the
+// passphrase stays locked until unlock_database is called. The previous database stays in
+// use when the new one cannot be opened.
+#[cfg(not(tarpaulin_include))]
+fn switch_database(
+    app_state: &AppState,
+    path: PathBuf,
+    passphrase: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = if passphrase.is_none() && encryption_service::is_encrypted(&path)? {
+        None
+    } else {
+        Some(prepare_database(&path, passphrase)?)
+    };
+
+    let mut current_db = app_state.db.lock().unwrap();
+    let mut current_path = app_state.db_path.lock().unwrap();
+    *current_db = db;
+    *current_path = Some(path);
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+fn app_dir(handle: &AppHandle) -> Result<PathBuf, String> {
+    handle
+        .path_resolver()
+        .app_data_dir()
+        .ok_or("The app data directory should exist.".to_string())
+}
+
+// Attachments are kept next to the database they belong to
+#[cfg(not(tarpaulin_include))]
+fn attachments_dir(handle: &AppHandle) -> Result<PathBuf, String> {
+    let app_state: State<AppState> = handle.state();
+    let db_path = app_state.db_path.lock().unwrap();
+    db_path
+        .as_ref()
+        .map(|path| attachment_service::attachments_dir(path))
+        .ok_or("No database is open".to_string())
+}
+
 #[tauri::command]
 #[cfg(not(tarpaulin_include))]
-fn get_categories(handle: AppHandle) -> Result<Vec<models::Category>, String> {
+fn get_categories(handle: AppHandle, include_archived: Option<bool>) -> Result<Vec<models::Category>, String> {
     handle
-        .db(|db| category_service::get_categories(db))
+        .db(|db| match include_archived {
+            Some(true) => category_service::query_categories(db, true),
+            _ => category_service::get_categories(db),
+        })
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 #[cfg(not(tarpaulin_include))]
-fn update_category_label(handle: AppHandle, id: i32, label: &str) -> Result<(), String> {
+fn update_category_label(handle: AppHandle, id: i32, label: &str) -> Result<models::CategoryOutcome, String> {
     handle
         .db(|db| category_service::update_category_label(db, id, label))
         .map_err(|e| e.to_string())
@@ -40,25 +113,377 @@ fn delete_category(handle: AppHandle, id: i32) -> Result<(), String> {
 
 #[tauri::command]
 #[cfg(not(tarpaulin_include))]
-fn insert_category(handle: AppHandle, label: &str) -> Result<(), String> {
+fn update_category_metadata(
+    handle: AppHandle,
+    id: i32,
+    color: Option<&str>,
+    icon: Option<&str>,
+    kind: models::CategoryKind,
+    archived: bool,
+) -> Result<(), String> {
+    handle
+        .db(|db| category_service::update_category_metadata(db, id, color, icon, kind, archived))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn set_category_order(handle: AppHandle, ids: Vec<i32>) -> Result<(), String> {
+    handle
+        .db(|db| category_service::set_category_order(db, ids))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_category_usage(handle: AppHandle) -> Result<Vec<models::CategoryUsage>, String> {
+    handle
+        .db(|db| category_service::get_category_usage(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn merge_categories(handle: AppHandle, source_ids: Vec<i32>, target_id: i32) -> Result<usize, String> {
+    handle
+        .db(|db| category_service::merge_categories(db, source_ids, target_id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn insert_category(handle: AppHandle, label: &str) -> Result<models::CategoryOutcome, String> {
     handle
         .db(|db| category_service::insert_category(db, label))
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_accounts(handle: AppHandle) -> Result<Vec<models::Account>, String> {
+    handle
+        .db(|db| account_service::get_accounts(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn insert_account(handle: AppHandle, label: &str, opening_balance: f64) -> Result<(), String> {
+    handle
+        .db(|db| account_service::insert_account(db, label, opening_balance))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn update_account(
+    handle: AppHandle,
+    id: i32,
+    label: &str,
+    opening_balance: f64,
+) -> Result<(), String> {
+    handle
+        .db(|db| account_service::update_account(db, id, label, opening_balance))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn delete_account(handle: AppHandle, id: i32) -> Result<(), String> {
+    handle
+        .db(|db| account_service::delete_account(db, id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_assets(handle: AppHandle) -> Result<Vec<models::Asset>, String> {
+    handle
+        .db(|db| asset_service::get_assets(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn insert_asset(handle: AppHandle, label: &str, kind: models::AssetKind) -> Result<(), String> {
+    handle
+        .db(|db| asset_service::insert_asset(db, label, kind))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn update_asset_label(handle: AppHandle, id: i32, label: &str) -> Result<(), String> {
+    handle
+        .db(|db| asset_service::update_asset_label(db, id, label))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn delete_asset(handle: AppHandle, id: i32) -> Result<(), String> {
+    handle
+        .db(|db| asset_service::delete_asset(db, id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_asset_valuations(
+    handle: AppHandle,
+    asset_id: i32,
+) -> Result<Vec<models::AssetValuation>, String> {
+    handle
+        .db(|db| asset_service::get_asset_valuations(db, asset_id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn set_asset_valuation(
+    handle: AppHandle,
+    asset_id: i32,
+    date: &str,
+    value: f64,
+) -> Result<(), String> {
+    handle
+        .db(|db| asset_service::set_asset_valuation(db, asset_id, date, value))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn delete_asset_valuation(handle: AppHandle, asset_id: i32, date: &str) -> Result<(), String> {
+    handle
+        .db(|db| asset_service::delete_asset_valuation(db, asset_id, date))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_recurring_transactions(
+    handle: AppHandle,
+) -> Result<Vec<models::RecurringTransaction>, String> {
+    handle
+        .db(|db| recurring_service::get_recurring_transactions(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn insert_recurring_transaction(
+    handle: AppHandle,
+    value: f64,
+    name: &str,
+    account_id: Option<i32>,
+    frequency: models::Frequency,
+    start_date: &str,
+    end_date: Option<&str>,
+) -> Result<(), String> {
+    handle
+        .db(|db| {
+            recurring_service::insert_recurring_transaction(
+                db, value, name, account_id, frequency, start_date, end_date,
+            )
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn delete_recurring_transaction(handle: AppHandle, id: i32) -> Result<(), String> {
+    handle
+        .db(|db| recurring_service::delete_recurring_transaction(db, id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_payees(handle: AppHandle) -> Result<Vec<models::Payee>, String> {
+    handle
+        .db(|db| payee_service::get_payees(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn insert_payee(handle: AppHandle, label: &str) -> Result<(), String> {
+    handle
+        .db(|db| payee_service::insert_payee(db, label))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn update_payee_label(handle: AppHandle, id: i32, label: &str) -> Result<(), String> {
+    handle
+        .db(|db| payee_service::update_payee_label(db, id, label))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn delete_payee(handle: AppHandle, id: i32) -> Result<(), String> {
+    handle
+        .db(|db| payee_service::delete_payee(db, id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn add_payee_pattern(handle: AppHandle, payee_id: i32, pattern: &str) -> Result<(), String> {
+    handle
+        .db(|db| payee_service::add_payee_pattern(db, payee_id, pattern))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn remove_payee_pattern(handle: AppHandle, pattern: &str) -> Result<(), String> {
+    handle
+        .db(|db| payee_service::remove_payee_pattern(db, pattern))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn apply_payee_patterns(handle: AppHandle) -> Result<usize, String> {
+    handle
+        .db(|db| payee_service::apply_payee_patterns(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_tags(handle: AppHandle) -> Result<Vec<models::Tag>, String> {
+    handle
+        .db(|db| tag_service::get_tags(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn insert_tag(handle: AppHandle, label: &str) -> Result<(), String> {
+    handle
+        .db(|db| tag_service::insert_tag(db, label))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn update_tag_label(handle: AppHandle, id: i32, label: &str) -> Result<(), String> {
+    handle
+        .db(|db| tag_service::update_tag_label(db, id, label))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn delete_tag(handle: AppHandle, id: i32) -> Result<(), String> {
+    handle
+        .db(|db| tag_service::delete_tag(db, id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn add_transaction_tag(handle: AppHandle, transaction_id: i32, tag_id: i32) -> Result<(), String> {
+    handle
+        .db(|db| tag_service::add_transaction_tag(db, transaction_id, tag_id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn remove_transaction_tag(
+    handle: AppHandle,
+    transaction_id: i32,
+    tag_id: i32,
+) -> Result<(), String> {
+    handle
+        .db(|db| tag_service::remove_transaction_tag(db, transaction_id, tag_id))
+        .map_err(|e| e.to_string())
+}
 
 // TODO should add extra query params like search, sort, date selectors
 #[tauri::command]
 #[cfg(not(tarpaulin_include))]
 fn get_transactions(
     handle: AppHandle,
-    page_size: i32,
+    page_size: Option<i32>,
     current_page: i32,
     search: &str,
     selected_categories: Vec<i32>,
+    selected_payees: Option<Vec<i32>>,
+    selected_tags: Option<Vec<i32>>,
 ) -> Result<models::Page, String> {
     handle
-        .db(|db| transaction_service::query_page(db, page_size, current_page, search, selected_categories))
+        .db(|db| {
+            let page_size = match page_size {
+                Some(page_size) => page_size,
+                None => settings_service::get_settings(db)?.page_size,
+            };
+            transaction_service::query_page(
+                db,
+                page_size,
+                current_page,
+                search,
+                selected_categories,
+                selected_payees.unwrap_or_default(),
+                selected_tags.unwrap_or_default(),
+            )
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn bulk_update(
+    handle: AppHandle,
+    selection: models::TransactionSelection,
+    operation: models::BulkOperation,
+) -> Result<usize, String> {
+    handle
+        .db(|db| bulk_service::bulk_update(db, &selection, &operation))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn set_transaction_bank_dates(
+    handle: AppHandle,
+    id: i32,
+    booking_date: Option<&str>,
+    value_date: Option<&str>,
+) -> Result<(), String> {
+    handle
+        .db(|db| transaction_service::set_transaction_bank_dates(db, id, booking_date, value_date))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_transactions_by_cursor(
+    handle: AppHandle,
+    page_size: Option<i32>,
+    filter: Option<models::TransactionFilter>,
+    cursor: Option<models::PageCursor>,
+    with_count: Option<bool>,
+) -> Result<models::CursorPage, String> {
+    handle
+        .db(|db| {
+            let page_size = match page_size {
+                Some(page_size) => page_size,
+                None => settings_service::get_settings(db)?.page_size,
+            };
+            transaction_service::query_cursor_page(
+                db,
+                page_size,
+                &filter.unwrap_or_default(),
+                cursor.as_ref(),
+                with_count.unwrap_or(false),
+            )
+        })
         .map_err(|e| e.to_string())
 }
 
@@ -78,8 +503,9 @@ fn insert_transaction(
     name: &str,
     description: Option<&str>,
     date_created: &str,
+    account_id: Option<i32>,
     category_ids: Vec<i32>,
-) -> Result<(), String> {
+) -> Result<models::Transaction, String> {
     handle
         .db(|db| {
             transaction_service::insert_transaction(
@@ -88,12 +514,59 @@ fn insert_transaction(
                 name,
                 description,
                 date_created,
+                account_id,
                 category_ids,
             )
         })
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_attachments(
+    handle: AppHandle,
+    transaction_id: i32,
+) -> Result<Vec<models::Attachment>, String> {
+    handle
+        .db(|db| attachment_service::get_attachments(db, transaction_id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn add_attachment(handle: AppHandle, transaction_id: i32, path: &str) -> Result<(), String> {
+    let dir = attachments_dir(&handle)?;
+    let path = PathBuf::from(path);
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or("Invalid file path".to_string())?
+        .to_string();
+    let content = fs::read(&path).map_err(|e| e.to_string())?;
+    handle
+        .db(|db| attachment_service::add_attachment(db, &dir, transaction_id, &file_name, &content))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn open_attachment(handle: AppHandle, id: i32) -> Result<(), String> {
+    let dir = attachments_dir(&handle)?;
+    let path = handle
+        .db(|db| attachment_service::get_attachment_path(db, &dir, id))
+        .map_err(|e| e.to_string())?;
+    open::that(path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn remove_attachment(handle: AppHandle, id: i32) -> Result<(), String> {
+    let dir = attachments_dir(&handle)?;
+    handle
+        .db(|db| attachment_service::remove_attachment(db, &dir, id))
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 #[cfg(not(tarpaulin_include))]
 fn get_report_types() -> Result<Vec<models::ReportType>, ()> {
@@ -102,18 +575,389 @@ fn get_report_types() -> Result<Vec<models::ReportType>, ()> {
 
 #[tauri::command]
 #[cfg(not(tarpaulin_include))]
-fn get_basic_report(handle: AppHandle, report_type: models::ReportType, selected_date: &str) -> Result<models::BasicReport, String> {
+fn get_basic_report(
+    handle: AppHandle,
+    report_type: models::ReportType,
+    selected_date: &str,
+    date_field: Option<models::DateField>,
+) -> Result<models::BasicReport, String> {
     handle
         .db(|db| {
-            report_service::get_basic_report(
+            report_service::get_basic_report_by(
                 db,
                 report_type,
                 selected_date,
+                date_field.unwrap_or(models::DateField::DATE_CREATED),
             )
         })
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_top_payees(
+    handle: AppHandle,
+    report_type: models::ReportType,
+    selected_date: &str,
+    limit: i32,
+    date_field: Option<models::DateField>,
+) -> Result<Vec<models::PayeeTotal>, String> {
+    handle
+        .db(|db| {
+            report_service::get_top_payees(
+                db,
+                report_type,
+                selected_date,
+                limit,
+                date_field.unwrap_or(models::DateField::DATE_CREATED),
+            )
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_tag_report(
+    handle: AppHandle,
+    start_date: &str,
+    end_date: &str,
+    date_field: Option<models::DateField>,
+) -> Result<Vec<models::TagTotal>, String> {
+    handle
+        .db(|db| {
+            report_service::get_tag_report(
+                db,
+                start_date,
+                end_date,
+                date_field.unwrap_or(models::DateField::DATE_CREATED),
+            )
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_comparison_report(
+    handle: AppHandle,
+    report_type: models::ReportType,
+    selected_date: &str,
+    period: models::ComparisonPeriod,
+    compare_date: Option<&str>,
+    date_field: Option<models::DateField>,
+) -> Result<models::ComparisonReport, String> {
+    handle
+        .db(|db| {
+            report_service::get_comparison_report(
+                db,
+                report_type,
+                selected_date,
+                period,
+                compare_date,
+                date_field.unwrap_or(models::DateField::DATE_CREATED),
+            )
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_trend_report(
+    handle: AppHandle,
+    selected_date: &str,
+    months: u32,
+    date_field: Option<models::DateField>,
+) -> Result<models::TrendReport, String> {
+    handle
+        .db(|db| {
+            report_service::get_trend_report(
+                db,
+                selected_date,
+                months,
+                date_field.unwrap_or(models::DateField::DATE_CREATED),
+            )
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_balance_history(
+    handle: AppHandle,
+    interval: models::BalanceInterval,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<models::AccountBalanceHistory>, String> {
+    handle
+        .db(|db| report_service::get_balance_history(db, interval, start_date, end_date))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_net_worth_report(
+    handle: AppHandle,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<models::NetWorthMonth>, String> {
+    handle
+        .db(|db| report_service::get_net_worth_report(db, start_date, end_date))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_forecast(
+    handle: AppHandle,
+    days: i64,
+    account_id: Option<i32>,
+    history_months: Option<u32>,
+) -> Result<models::Forecast, String> {
+    let today = chrono::Local::now().date_naive().format("%Y-%m-%d").to_string();
+    handle
+        .db(|db| {
+            report_service::get_forecast(db, &today, days, account_id, history_months.unwrap_or(0))
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_trash(handle: AppHandle) -> Result<models::Trash, String> {
+    handle
+        .db(|db| trash_service::get_trash(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn restore_transaction(handle: AppHandle, id: i32) -> Result<(), String> {
+    handle
+        .db(|db| trash_service::restore_transaction(db, id))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn restore_category(handle: AppHandle, id: i32) -> Result<(), String> {
+    handle
+        .db(|db| trash_service::restore_category(db, id))
+        .map_err(|e| e.to_string())
+}
+
+// Attachments of purged transactions are removed with them
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn purge_trash(handle: AppHandle, retention_days: Option<u32>) -> Result<usize, String> {
+    let dir = attachments_dir(&handle)?;
+    handle
+        .db(|db| -> Result<usize, Box<dyn std::error::Error>> {
+            let retention_days = match retention_days {
+                Some(retention_days) => retention_days,
+                None => settings_service::get_settings(db)?.trash_retention_days,
+            };
+            let purged = trash_service::purge_trash(db, retention_days)?;
+            attachment_service::remove_orphaned_files(db, &dir)?;
+            Ok(purged)
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_audit_log(
+    handle: AppHandle,
+    entity: Option<&str>,
+    entity_id: Option<&str>,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<Vec<models::AuditEntry>, String> {
+    handle
+        .db(|db| audit_service::get_audit_log(db, entity, entity_id, start_date, end_date))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn undo(handle: AppHandle) -> Result<Option<String>, String> {
+    handle
+        .db(|db| journal_service::undo(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn redo(handle: AppHandle) -> Result<Option<String>, String> {
+    handle
+        .db(|db| journal_service::redo(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn is_database_locked(handle: AppHandle) -> bool {
+    let app_state: State<AppState> = handle.state();
+    let locked = app_state.db.lock().unwrap().is_none();
+    locked
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn unlock_database(handle: AppHandle, passphrase: &str) -> Result<(), String> {
+    let app_state: State<AppState> = handle.state();
+    let path = app_state
+        .db_path
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No database is open".to_string())?;
+    let db = prepare_database(&path, Some(passphrase)).map_err(|e| e.to_string())?;
+    *app_state.db.lock().unwrap() = Some(db);
+    Ok(())
+}
+
+// An empty passphrase removes the encryption, setting a passphrase on a plain database
+// encrypts it
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn change_passphrase(handle: AppHandle, passphrase: Option<&str>) -> Result<(), String> {
+    let app_state: State<AppState> = handle.state();
+    let path = app_state
+        .db_path
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No database is open".to_string())?;
+
+    let mut db = app_state.db.lock().unwrap();
+    encryption_service::change_passphrase(&mut db, &path, passphrase).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_profiles(handle: AppHandle) -> Result<Vec<String>, String> {
+    profile_service::get_profiles(&app_dir(&handle)?).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_active_profile(handle: AppHandle) -> Result<String, String> {
+    Ok(profile_service::get_active_profile(&app_dir(&handle)?))
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn create_profile(handle: AppHandle, name: &str) -> Result<(), String> {
+    profile_service::create_profile(&app_dir(&handle)?, name)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn switch_profile(handle: AppHandle, name: &str) -> Result<(), String> {
+    let app_dir = app_dir(&handle)?;
+    let app_state: State<AppState> = handle.state();
+    let name = profile_service::validate_name(name).map_err(|e| e.to_string())?;
+    let path = profile_service::profile_path(&app_dir, &name);
+    if !path.exists() {
+        return Err(format!("Profile {:?} does not exist", name));
+    }
+
+    switch_database(&app_state, path, None).map_err(|e| e.to_string())?;
+    profile_service::set_active_profile(&app_dir, &name).map_err(|e| e.to_string())
+}
+
+// The active profile is closed while its database file is renamed
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn rename_profile(handle: AppHandle, name: &str, new_name: &str) -> Result<(), String> {
+    let app_dir = app_dir(&handle)?;
+    let app_state: State<AppState> = handle.state();
+    if profile_service::get_active_profile(&app_dir) != name.trim() {
+        return profile_service::rename_profile(&app_dir, name, new_name).map_err(|e| e.to_string());
+    }
+
+    *app_state.db.lock().unwrap() = None;
+    *app_state.db_path.lock().unwrap() = None;
+    let renamed = profile_service::rename_profile(&app_dir, name, new_name);
+    let profile = profile_service::get_active_profile(&app_dir);
+    switch_database(&app_state, profile_service::profile_path(&app_dir, &profile), None)
+        .map_err(|e| e.to_string())?;
+    renamed.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn delete_profile(handle: AppHandle, name: &str) -> Result<(), String> {
+    profile_service::delete_profile(&app_dir(&handle)?, name).map_err(|e| e.to_string())
+}
+
+// Opens a database outside of the profiles, e.g. from a synced folder. An empty database is
+// only initialized when asked to, otherwise EMPTY is returned so that the user can be asked
+// first. ENCRYPTED is returned without switching so that the user can be asked for the
+// passphrase, with the passphrase the database is verified before it is opened. The profile
+// opened at the next start does not change.
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn open_database(
+    handle: AppHandle,
+    path: &str,
+    passphrase: Option<&str>,
+    initialize: Option<bool>,
+) -> Result<models::DatabaseKind, String> {
+    let app_dir = app_dir(&handle)?;
+    let app_state: State<AppState> = handle.state();
+    let path = PathBuf::from(path);
+
+    let kind = database_service::get_database_kind(&path, passphrase).map_err(|e| e.to_string())?;
+    match kind {
+        models::DatabaseKind::FINANCE_APP => {}
+        models::DatabaseKind::EMPTY if initialize.unwrap_or(false) => {}
+        models::DatabaseKind::EMPTY | models::DatabaseKind::ENCRYPTED => return Ok(kind),
+        models::DatabaseKind::OTHER => {
+            return Err(format!("{:?} is not a finance-app database", path));
+        }
+    }
+
+    switch_database(&app_state, path.clone(), passphrase).map_err(|e| e.to_string())?;
+    database_service::add_recent_file(&app_dir, &path).map_err(|e| e.to_string())?;
+    Ok(kind)
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_recent_files(handle: AppHandle) -> Result<Vec<String>, String> {
+    Ok(database_service::get_recent_files(&app_dir(&handle)?))
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn get_settings(handle: AppHandle) -> Result<models::Settings, String> {
+    handle
+        .db(|db| settings_service::get_settings(db))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn set_setting(
+    handle: AppHandle,
+    key: &str,
+    value: serde_json::Value,
+) -> Result<models::Settings, String> {
+    handle
+        .db(|db| settings_service::set_setting(db, key, &value))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[cfg(not(tarpaulin_include))]
+fn reset_setting(handle: AppHandle, key: &str) -> Result<models::Settings, String> {
+    handle
+        .db(|db| settings_service::reset_setting(db, key))
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 #[cfg(not(tarpaulin_include))]
 fn reset_database(handle: AppHandle) -> Result<(), String> {
@@ -127,6 +971,7 @@ fn main() {
     tauri::Builder::default()
         .manage(AppState {
             db: Default::default(),
+            db_path: Default::default(),
         })
         .setup(|app| {
             let handle = app.handle();
@@ -141,24 +986,87 @@ fn main() {
 
             fs::create_dir_all(&app_dir).expect("The app data directory should be created.");
 
-            let sqlite_path = app_dir.join("finance-app.sqlite");
-
-            let db = Connection::open(sqlite_path)?;
-            migration_service::init_tables(&db).expect("Failed to initialize database");
-
-            *app_state.db.lock().unwrap() = Some(db);
+            profile_service::init_profiles(&app_dir)?;
+            let profile = profile_service::get_active_profile(&app_dir);
+            switch_database(&app_state, profile_service::profile_path(&app_dir, &profile), None)?;
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             get_categories,
             delete_category,
+            merge_categories,
+            get_category_usage,
+            update_category_metadata,
+            set_category_order,
             update_category_label,
             insert_category,
+            get_accounts,
+            insert_account,
+            update_account,
+            delete_account,
+            get_assets,
+            insert_asset,
+            update_asset_label,
+            delete_asset,
+            get_asset_valuations,
+            set_asset_valuation,
+            delete_asset_valuation,
+            get_recurring_transactions,
+            insert_recurring_transaction,
+            delete_recurring_transaction,
+            get_payees,
+            insert_payee,
+            update_payee_label,
+            delete_payee,
+            add_payee_pattern,
+            remove_payee_pattern,
+            apply_payee_patterns,
+            get_tags,
+            insert_tag,
+            update_tag_label,
+            delete_tag,
+            add_transaction_tag,
+            remove_transaction_tag,
             get_transactions,
+            get_transactions_by_cursor,
             delete_transaction,
+            set_transaction_bank_dates,
+            bulk_update,
             insert_transaction,
+            get_attachments,
+            add_attachment,
+            open_attachment,
+            remove_attachment,
             get_report_types,
             get_basic_report,
+            get_top_payees,
+            get_tag_report,
+            get_comparison_report,
+            get_trend_report,
+            get_balance_history,
+            get_net_worth_report,
+            get_forecast,
+            get_trash,
+            restore_transaction,
+            restore_category,
+            purge_trash,
+            get_audit_log,
+            undo,
+            redo,
+            is_database_locked,
+            unlock_database,
+            change_passphrase,
+            get_profiles,
+            get_active_profile,
+            create_profile,
+            switch_profile,
+            rename_profile,
+            delete_profile,
+            open_database,
+            get_recent_files,
+            get_settings,
+            set_setting,
+            reset_setting,
             reset_database
         ])
         .run(tauri::generate_context!())
@@ -1,4 +1,5 @@
-use rusqlite::Connection;
+use chrono::{DateTime, NaiveDate};
+use rusqlite::{named_params, Connection};
 
 pub fn init_tables(db: &Connection) -> Result<(), rusqlite::Error> {
 
@@ -27,17 +28,286 @@ pub fn init_tables(db: &Connection) -> Result<(), rusqlite::Error> {
           FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE CASCADE,
           FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
         );
+
+        CREATE TABLE IF NOT EXISTS accounts(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          label TEXT NOT NULL UNIQUE,
+          opening_balance REAL NOT NULL DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS assets(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          label TEXT NOT NULL UNIQUE,
+          kind TEXT NOT NULL CHECK (kind IN ('ASSET', 'LIABILITY'))
+        );
+
+        CREATE TABLE IF NOT EXISTS asset_valuations(
+          asset_id INTEGER NOT NULL,
+          date TEXT NOT NULL,
+          value REAL NOT NULL, -- liabilities are stored as positive amounts owed
+          PRIMARY KEY (asset_id, date),
+          FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS recurring_transactions(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          value REAL NOT NULL,
+          name TEXT NOT NULL,
+          account_id INTEGER,
+          frequency TEXT NOT NULL CHECK (frequency IN ('WEEKLY', 'MONTHLY', 'YEARLY')),
+          start_date TEXT NOT NULL, -- date of the first occurrence
+          end_date TEXT, -- optional date after which there are no more occurrences
+          FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE SET NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS payees(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          label TEXT NOT NULL UNIQUE
+        );
+
+        CREATE TABLE IF NOT EXISTS payee_patterns(
+          pattern TEXT PRIMARY KEY, -- LIKE pattern matched against the name of a transaction
+          payee_id INTEGER NOT NULL,
+          FOREIGN KEY (payee_id) REFERENCES payees(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS tags(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          label TEXT NOT NULL UNIQUE
+        );
+
+        CREATE TABLE IF NOT EXISTS transaction_tags(
+          transaction_id INTEGER NOT NULL,
+          tag_id INTEGER NOT NULL,
+          PRIMARY KEY (transaction_id, tag_id),
+          FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE,
+          FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS attachments(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          transaction_id INTEGER NOT NULL,
+          file_name TEXT NOT NULL, -- original name of the file
+          hash TEXT NOT NULL, -- sha256 of the content, also the name of the stored file
+          size INTEGER NOT NULL,
+          date_added TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+          FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS journal_operations(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          label TEXT NOT NULL, -- name of the operation, e.g. delete_transaction
+          undone INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS journal_changes(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          operation_id INTEGER NOT NULL,
+          entity TEXT NOT NULL,
+          entity_id INTEGER NOT NULL,
+          before TEXT, -- JSON snapshot of the entity, NULL when it did not exist
+          after TEXT,
+          FOREIGN KEY (operation_id) REFERENCES journal_operations(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS audit_log(
+          id INTEGER PRIMARY KEY AUTOINCREMENT,
+          entity TEXT NOT NULL, -- name of the changed table
+          entity_id INTEGER, -- the key of settings rows
+          operation TEXT NOT NULL CHECK(operation IN ('INSERT', 'UPDATE', 'DELETE')),
+          before TEXT, -- JSON of the row, NULL for inserts
+          after TEXT, -- JSON of the row, NULL for deletes
+          timestamp TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );
+
+        CREATE INDEX IF NOT EXISTS audit_log_entity ON audit_log(entity, entity_id);
+        CREATE INDEX IF NOT EXISTS audit_log_timestamp ON audit_log(timestamp);
+
+        CREATE TABLE IF NOT EXISTS settings(
+          key TEXT PRIMARY KEY,
+          value TEXT NOT NULL -- JSON value, validated by settings_service
+        );
         ",
     )?;
+
+    run_migrations(db)?;
+
+    // the triggers follow the columns of the tables, so they are created after the migrations
+    crate::audit_service::create_audit_triggers(db)?;
+    Ok(())
+}
+
+// Tables above are created in their original shape, any later change to an existing table is
+// done here. The index of a migration + 1 is stored as the schema version in user_version.
+type Migration = fn(&Connection) -> Result<(), rusqlite::Error>;
+
+const MIGRATIONS: &[Migration] = &[
+    add_transaction_account,
+    add_transaction_payee,
+    add_soft_delete,
+    normalize_transaction_dates,
+    add_transaction_timestamps,
+    add_category_metadata,
+    add_category_display_label,
+    add_transaction_indexes,
+];
+
+fn run_migrations(db: &Connection) -> Result<(), rusqlite::Error> {
+    let version: usize = db.query_row("PRAGMA user_version;", [], |row| row.get(0))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        migration(db)?;
+        db.pragma_update(None, "user_version", index + 1)?;
+    }
     Ok(())
 }
 
+fn add_transaction_account(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute_batch(
+        "
+        ALTER TABLE transactions
+        ADD COLUMN account_id INTEGER REFERENCES accounts(id) ON DELETE SET NULL;
+        ",
+    )
+}
+
+fn add_transaction_payee(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute_batch(
+        "
+        ALTER TABLE transactions
+        ADD COLUMN payee_id INTEGER REFERENCES payees(id) ON DELETE SET NULL;
+        ",
+    )
+}
+
+// deleted rows stay in the trash until they are restored or purged
+fn add_soft_delete(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute_batch(
+        "
+        ALTER TABLE transactions ADD COLUMN deleted_at TEXT;
+        ALTER TABLE categories ADD COLUMN deleted_at TEXT;
+        ",
+    )
+}
+
+// Date of a transaction in the stored format, also accepts the formats written by older versions
+pub fn normalize_date(date: &str) -> Option<String> {
+    crate::shared_service::parse_date_time(date)
+        .ok()
+        .or_else(|| {
+            ["%Y/%m/%d", "%d.%m.%Y"]
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(date.trim(), format).ok())
+                .map(|date| (date, None))
+        })
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(date.trim())
+                .ok()
+                .map(|date| (date.date_naive(), Some(date.time())))
+        })
+        .map(|(date, time)| crate::shared_service::format_date_time(date, time))
+}
+
+// Older versions stored any text the frontend sent as the date. Dates in a known format are
+// rewritten as YYYY-MM-DD (with HH:MM:SS when there is a time), dates which cannot be read are
+// set to 1970-01-01 and the original text is kept in the description.
+fn normalize_transaction_dates(db: &Connection) -> Result<(), rusqlite::Error> {
+    let mut stmt = db.prepare("SELECT id, date_created FROM transactions;")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(i32, String)>, rusqlite::Error>>()?;
+
+    for (id, date) in rows {
+        match normalize_date(&date) {
+            Some(normalized) if normalized == date => {}
+            Some(normalized) => {
+                db.execute(
+                    "UPDATE transactions SET date_created = (:date) WHERE id = (:id);",
+                    named_params! { ":date": normalized, ":id": id },
+                )?;
+            }
+            None => {
+                db.execute(
+                    "
+                    UPDATE transactions
+                    SET date_created = '1970-01-01',
+                    description = 'Invalid date: ' || date_created || COALESCE(' ' || description, '')
+                    WHERE id = (:id);
+                    ",
+                    named_params! { ":id": id },
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// created_at and updated_at are set by the app, booking_date and value_date are optional dates
+// provided by banks next to the purchase date in date_created
+fn add_transaction_timestamps(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute_batch(
+        "
+        ALTER TABLE transactions ADD COLUMN created_at TEXT;
+        ALTER TABLE transactions ADD COLUMN updated_at TEXT;
+        ALTER TABLE transactions ADD COLUMN booking_date TEXT;
+        ALTER TABLE transactions ADD COLUMN value_date TEXT;
+        ",
+    )
+}
+
+// Categories without a color get one from a palette by their id, see category_service
+fn add_category_metadata(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute_batch(
+        "
+        ALTER TABLE categories ADD COLUMN color TEXT;
+        ALTER TABLE categories ADD COLUMN icon TEXT;
+        ALTER TABLE categories ADD COLUMN kind TEXT NOT NULL DEFAULT 'BOTH';
+        ALTER TABLE categories ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE categories ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+        ",
+    )
+}
+
+// Labels are stored in lowercase to keep them unique regardless of casing, the display label
+// keeps the casing the category was named with
+fn add_category_display_label(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute_batch("ALTER TABLE categories ADD COLUMN display_label TEXT;")
+}
+
+// Transactions are listed and reported by date. The running balance is summed up per account
+// from the index alone, which is why it includes the value.
+fn add_transaction_indexes(db: &Connection) -> Result<(), rusqlite::Error> {
+    db.execute_batch(
+        "
+        CREATE INDEX IF NOT EXISTS transactions_date_created ON transactions(date_created, id);
+        CREATE INDEX IF NOT EXISTS transactions_account_balance
+        ON transactions(account_id, date_created, value, deleted_at);
+        CREATE INDEX IF NOT EXISTS transaction_categories_category
+        ON transaction_categories(category_id);
+        ",
+    )
+}
+
 fn drop_tables(db: &Connection) -> Result<(), rusqlite::Error> {
     db.execute_batch(
         "
         DROP TABLE IF EXISTS transaction_categories;
+        DROP TABLE IF EXISTS transaction_tags;
+        DROP TABLE IF EXISTS attachments;
+        DROP TABLE IF EXISTS journal_changes;
+        DROP TABLE IF EXISTS journal_operations;
+        DROP TABLE IF EXISTS audit_log;
+        DROP TABLE IF EXISTS settings;
+        DROP TABLE IF EXISTS tags;
         DROP TABLE IF EXISTS transactions;
+        DROP TABLE IF EXISTS recurring_transactions;
         DROP TABLE IF EXISTS categories;
+        DROP TABLE IF EXISTS accounts;
+        DROP TABLE IF EXISTS payee_patterns;
+        DROP TABLE IF EXISTS payees;
+        DROP TABLE IF EXISTS asset_valuations;
+        DROP TABLE IF EXISTS assets;
+        PRAGMA user_version = 0;
         "
     )?;
     Ok(())
@@ -57,3 +327,31 @@ pub fn init_db_in_memory() -> Result<Connection, rusqlite::Error> {
     init_tables(&mut db)?;
     Ok(db)
 }
+
+#[test]
+fn migration_should_normalize_transaction_dates() -> Result<(), rusqlite::Error> {
+    let db = Connection::open_in_memory()?;
+    init_tables(&db)?;
+    db.execute_batch(
+        "
+        INSERT INTO transactions (value, name, date_created) VALUES
+        (1, 'iso', '2023-11-01'),
+        (1, 'time', '2023-11-01T10:30'),
+        (1, 'dotted', '01.11.2023'),
+        (1, 'broken', 'yesterday');
+        ",
+    )?;
+
+    normalize_transaction_dates(&db)?;
+
+    let mut stmt = db.prepare("SELECT date_created, description FROM transactions ORDER BY id;")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(String, Option<String>)>, rusqlite::Error>>()?;
+    assert_eq!(rows[0].0, "2023-11-01");
+    assert_eq!(rows[1].0, "2023-11-01 10:30:00");
+    assert_eq!(rows[2].0, "2023-11-01");
+    assert_eq!(rows[3].0, "1970-01-01");
+    assert_eq!(rows[3].1, Some("Invalid date: yesterday".to_string()));
+    Ok(())
+}
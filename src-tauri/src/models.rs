@@ -1,12 +1,73 @@
+use chrono::{NaiveDate, NaiveTime};
 use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
 pub enum ReportType {
     MONTH,
     YEAR
 }
 
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum BalanceInterval {
+    DAY,
+    MONTH
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum AssetKind {
+    ASSET,
+    LIABILITY
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum Frequency {
+    WEEKLY,
+    MONTHLY,
+    YEARLY
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum ComparisonPeriod {
+    PREVIOUS_PERIOD,
+    SAME_PERIOD_LAST_YEAR,
+    CUSTOM
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum FirstDayOfWeek {
+    MONDAY,
+    SATURDAY,
+    SUNDAY
+}
+
+// Date of a transaction used to place it in a report period, the booking and value dates
+// fall back to date_created when a transaction does not have them
+#[allow(non_camel_case_types)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum DateField {
+    DATE_CREATED,
+    BOOKING_DATE,
+    VALUE_DATE
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum CategoryKind {
+    INCOME,
+    EXPENSE,
+    BOTH
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum DatabaseKind {
+    FINANCE_APP,
+    EMPTY,
+    ENCRYPTED,
+    OTHER
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct BasicReport {
     pub total: f64,
@@ -14,6 +75,7 @@ pub struct BasicReport {
     pub dates: HashMap<String, f64>,
     pub category_income: HashMap<String, f64>,
     pub category_expenses: HashMap<String, f64>,
+    pub category_colors: HashMap<String, String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -36,16 +98,281 @@ pub struct ReportByCategory {
     pub total: f64,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CategoryDelta {
+    pub label: String,
+    pub current: f64,
+    pub previous: f64,
+    pub delta: f64,
+    pub percentage: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ComparisonReport {
+    pub current_date: String,
+    pub previous_date: String,
+    pub total: CategoryDelta,
+    pub categories: Vec<CategoryDelta>,
+    pub appeared: Vec<String>,
+    pub vanished: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CategoryTrend {
+    pub id: i32,
+    pub label: String,
+    pub months: HashMap<String, f64>,
+    pub average: f64,
+    pub min: f64,
+    pub max: f64,
+    pub slope: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TrendReport {
+    pub months: Vec<String>,
+    pub categories: Vec<CategoryTrend>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PayeeTotal {
+    pub payee_id: i32,
+    pub label: String,
+    pub total: f64,
+    pub count: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagTotal {
+    pub tag_id: i32,
+    pub label: String,
+    pub total: f64,
+    pub count: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AccountBalanceHistory {
+    pub account_id: Option<i32>,
+    pub label: String,
+    pub dates: HashMap<String, f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NetWorthMonth {
+    pub month: String,
+    pub accounts: f64,
+    pub assets: f64,
+    pub liabilities: f64,
+    pub net_worth: f64,
+    pub accounts_change: f64,
+    pub assets_change: f64,
+    pub liabilities_change: f64,
+    pub net_worth_change: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ForecastPoint {
+    pub date: String,
+    pub balance: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Forecast {
+    pub starting_balance: f64,
+    pub dates: HashMap<String, f64>,
+    pub average_category_spending: HashMap<String, f64>,
+    pub lowest: ForecastPoint,
+    pub low_points: Vec<ForecastPoint>,
+    pub first_negative_date: Option<String>,
+}
+
+// Same filters as the transaction list
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct TransactionFilter {
+    pub search: String,
+    pub categories: Vec<i32>,
+    pub payees: Vec<i32>,
+    pub tags: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum TransactionSelection {
+    IDS(Vec<i32>),
+    FILTER(TransactionFilter),
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Serialize, Deserialize, Debug)]
+pub enum BulkOperation {
+    DELETE,
+    ADD_CATEGORY(i32),
+    REMOVE_CATEGORY(i32),
+    REPLACE_CATEGORIES(Vec<i32>),
+    SET_DATE(String),
+    MOVE_TO_ACCOUNT(Option<i32>),
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Page {
     pub total_pages: i32,
     pub transactions: Vec<Transaction>,
 }
 
+// Position of a transaction in the transaction list, which is ordered by date_created and id
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TransactionCursor {
+    pub date_created: String,
+    pub id: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum PageCursor {
+    AFTER(TransactionCursor),
+    BEFORE(TransactionCursor),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CursorPage {
+    pub transactions: Vec<Transaction>,
+    pub next: Option<TransactionCursor>, // None on the last page
+    pub previous: Option<TransactionCursor>, // None on the first page
+    pub total_count: Option<i32>, // only counted when asked for
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Category {
     pub id: i32,
     pub label: String,
+    pub display_label: String, // label with the casing it was named with
+    pub color: String, // chosen by the user or picked from a palette
+    pub icon: Option<String>,
+    pub kind: CategoryKind,
+    pub sort_order: i32,
+    pub archived: bool, // hidden when picking categories but kept on transactions
+}
+
+// Result of inserting or renaming a category, a label can be used by only one category
+#[allow(non_camel_case_types)]
+#[derive(Serialize, Deserialize, Debug)]
+pub enum CategoryOutcome {
+    CREATED(Category),
+    RESTORED(Category), // a category with the label was in the trash
+    ALREADY_EXISTS(Category),
+    UPDATED(Category),
+    CONFLICT(Category), // the category already using the label
+    CONFLICT_IN_TRASH(Category), // the category in the trash using the label
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CategoryUsage {
+    pub category_id: i32,
+    pub label: String,
+    pub transaction_count: i32,
+    pub total: f64,
+    pub first_used: Option<String>,
+    pub last_used: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Tag {
+    pub id: i32,
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Attachment {
+    pub id: i32,
+    pub transaction_id: i32,
+    pub file_name: String,
+    pub hash: String,
+    pub size: i64,
+    pub date_added: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrashedTransaction {
+    pub id: i32,
+    pub value: f64,
+    pub name: String,
+    pub description: Option<String>,
+    pub date_created: NaiveDate,
+    pub time_created: Option<NaiveTime>,
+    pub deleted_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrashedCategory {
+    pub id: i32,
+    pub label: String,
+    pub deleted_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Trash {
+    pub transactions: Vec<TrashedTransaction>,
+    pub categories: Vec<TrashedCategory>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub entity: String,
+    pub entity_id: Option<String>, // the key of a setting or the id of any other entity
+    pub operation: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub timestamp: String,
+}
+
+// Every setting has a default, see settings_service
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Settings {
+    pub page_size: i32,
+    pub base_currency: String,
+    pub first_day_of_week: FirstDayOfWeek,
+    pub date_format: String,
+    pub default_report_type: ReportType,
+    pub trash_retention_days: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Account {
+    pub id: i32,
+    pub label: String,
+    pub opening_balance: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Asset {
+    pub id: i32,
+    pub label: String,
+    pub kind: AssetKind,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AssetValuation {
+    pub asset_id: i32,
+    pub date: String,
+    pub value: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RecurringTransaction {
+    pub id: i32,
+    pub value: f64,
+    pub name: String,
+    pub account_id: Option<i32>,
+    pub frequency: Frequency,
+    pub start_date: String,
+    pub end_date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Payee {
+    pub id: i32,
+    pub label: String,
+    pub patterns: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -54,8 +381,17 @@ pub struct Transaction {
     pub value: f64,
     pub name: String,
     pub description: Option<String>,
-    pub date_created: Option<String>,
-    pub categories: Vec<Category>
+    pub date_created: NaiveDate,
+    pub time_created: Option<NaiveTime>, // not every transaction has a time of day
+    pub booking_date: Option<NaiveDate>,
+    pub value_date: Option<NaiveDate>,
+    pub created_at: Option<String>, // unknown for transactions created before it was tracked
+    pub updated_at: Option<String>,
+    pub account_id: Option<i32>,
+    pub payee_id: Option<i32>,
+    pub running_balance: f64,
+    pub categories: Vec<Category>,
+    pub tags: Vec<Tag>
 }
 
 #[derive(Serialize, Deserialize)]
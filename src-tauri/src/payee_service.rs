@@ -0,0 +1,239 @@
+use crate::models::Payee;
+use rusqlite::{named_params, Connection};
+use std::collections::HashMap;
+
+pub fn insert_payee(db: &Connection, label: &str) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "INSERT OR IGNORE INTO payees (label) VALUES (:label);",
+        named_params! {
+            ":label": label.trim(),
+        },
+    )?;
+    Ok(())
+}
+
+pub fn update_payee_label(db: &Connection, id: i32, label: &str) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "UPDATE payees SET label = (:label) WHERE id = (:id);",
+        named_params! {
+            ":label": label.trim(),
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn delete_payee(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "DELETE FROM payees WHERE id = (:id);",
+        named_params! {
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn get_payees(db: &Connection) -> Result<Vec<Payee>, rusqlite::Error> {
+    let mut patterns: HashMap<i32, Vec<String>> = HashMap::new();
+    let mut pattern_statement = db.prepare("SELECT payee_id, pattern FROM payee_patterns;")?;
+    let mut pattern_rows = pattern_statement.query([])?;
+    while let Some(row) = pattern_rows.next()? {
+        let pattern: String = row.get(1)?;
+        patterns
+            .entry(row.get(0)?)
+            .or_default()
+            .push(pattern.replace('%', "*"));
+    }
+
+    let mut stmt = db.prepare("SELECT id, label FROM payees;")?;
+    let mut rows = stmt.query([])?;
+    let mut payees = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id = row.get(0)?;
+        payees.push(Payee {
+            id,
+            label: row.get(1)?,
+            patterns: patterns.remove(&id).unwrap_or_default(),
+        });
+    }
+    Ok(payees)
+}
+
+// Patterns are stored with '%' as their only wildcard, a '%' typed by the user has no meaning
+fn normalize_pattern(pattern: &str) -> String {
+    pattern.trim().replace('%', "").replace('*', "%")
+}
+
+// Patterns are matched case-insensitively against the whole name of a transaction,
+// '*' matches any text so "amazon*" matches "AMAZON MKTPLACE 123".
+// A pattern without wildcards works as an alias of the payee.
+pub fn add_payee_pattern(db: &Connection, payee_id: i32, pattern: &str) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "
+        INSERT INTO payee_patterns (pattern, payee_id) VALUES (:pattern, :payee_id)
+        ON CONFLICT (pattern) DO UPDATE SET payee_id = excluded.payee_id;
+        ",
+        named_params! {
+            ":pattern": normalize_pattern(pattern),
+            ":payee_id": payee_id,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn remove_payee_pattern(db: &Connection, pattern: &str) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "DELETE FROM payee_patterns WHERE pattern = (:pattern);",
+        named_params! {
+            ":pattern": normalize_pattern(pattern),
+        },
+    )?;
+    Ok(())
+}
+
+// The payee whose label or pattern matches the name, when several patterns match
+// the longest one is considered the most specific. Apart from the wildcard of a pattern
+// every character of labels and patterns is matched literally.
+pub fn resolve_payee(db: &Connection, name: &str) -> Result<Option<i32>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "
+        SELECT id FROM (
+            SELECT
+            id,
+            label as pattern,
+            replace(replace(replace(label, '\\', '\\\\'), '%', '\\%'), '_', '\\_') as like_pattern
+            FROM payees
+            UNION ALL
+            SELECT
+            payee_id as id,
+            pattern,
+            replace(replace(pattern, '\\', '\\\\'), '_', '\\_') as like_pattern
+            FROM payee_patterns
+        )
+        WHERE (:name) LIKE like_pattern ESCAPE '\\'
+        ORDER BY length(pattern) DESC
+        LIMIT 1
+        ",
+    )?;
+    let mut rows = stmt.query(named_params! {
+        ":name": name.trim(),
+    })?;
+
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+// Resolves the payee for transactions which don't have one yet, for example after new patterns
+// were added or after importing transactions. Returns the number of updated transactions.
+pub fn apply_payee_patterns(db: &Connection) -> Result<usize, rusqlite::Error> {
+    let mut stmt = db.prepare("SELECT id, name FROM transactions WHERE payee_id IS NULL AND deleted_at IS NULL;")?;
+    let mut rows = stmt.query([])?;
+
+    let mut resolved: Vec<(i32, i32)> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(1)?;
+        if let Some(payee_id) = resolve_payee(db, &name)? {
+            resolved.push((row.get(0)?, payee_id));
+        }
+    }
+
+    for (id, payee_id) in &resolved {
+        db.execute(
+            "UPDATE transactions SET payee_id = (:payee_id), updated_at = CURRENT_TIMESTAMP WHERE id = (:id);",
+            named_params! {
+                ":payee_id": payee_id,
+                ":id": id,
+            },
+        )?;
+    }
+    Ok(resolved.len())
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn after_insert_should_be_readable() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_payee(&conn, "Amazon")?;
+    let id = get_payees(&conn)?[0].id;
+    add_payee_pattern(&conn, id, "amazon mktplace*")?;
+    add_payee_pattern(&conn, id, "Amazon.de")?;
+
+    let list = get_payees(&conn)?;
+    assert!(list.len() == 1, "Expected list with one item, got {:?}", list);
+    assert_eq!(list[0].patterns.len(), 2);
+    assert!(list[0].patterns.contains(&"amazon mktplace*".to_string()));
+    Ok(())
+}
+
+#[test]
+fn resolve_should_match_label_aliases_and_patterns() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_payee(&conn, "Amazon")?;
+    insert_payee(&conn, "Amazon Prime")?;
+    let payees = get_payees(&conn)?;
+    add_payee_pattern(&conn, payees[0].id, "amazon*")?;
+    add_payee_pattern(&conn, payees[1].id, "amazon prime*")?;
+
+    assert_eq!(resolve_payee(&conn, "amazon")?, Some(payees[0].id));
+    assert_eq!(resolve_payee(&conn, "AMAZON MKTPLACE 123")?, Some(payees[0].id));
+    assert_eq!(resolve_payee(&conn, "Amazon Prime*AB12")?, Some(payees[1].id));
+    assert_eq!(resolve_payee(&conn, "grocery store")?, None);
+    Ok(())
+}
+
+#[test]
+fn resolve_should_match_special_characters_literally() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_payee(&conn, "100% Organic")?;
+    insert_payee(&conn, "Shop")?;
+    let payees = get_payees(&conn)?;
+    add_payee_pattern(&conn, payees[1].id, "shop_*")?;
+
+    assert_eq!(resolve_payee(&conn, "100% organic")?, Some(payees[0].id));
+    assert_eq!(resolve_payee(&conn, "100 organic")?, None);
+    assert_eq!(resolve_payee(&conn, "100 percent organic")?, None);
+    assert_eq!(resolve_payee(&conn, "shop_berlin")?, Some(payees[1].id));
+    assert_eq!(resolve_payee(&conn, "shops")?, None);
+
+    // '%' is dropped the same way when adding and removing a pattern
+    add_payee_pattern(&conn, payees[1].id, "store%*")?;
+    remove_payee_pattern(&conn, "store%*")?;
+    assert_eq!(get_payees(&conn)?[1].patterns, vec!["shop_*".to_string()]);
+    Ok(())
+}
+
+#[test]
+fn apply_should_resolve_existing_transactions() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::transaction_service::insert_transaction(&conn, -1.0, "AMAZON MKTPLACE 123", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -1.0, "grocery store", None, "2023-11-01", None, vec![])?;
+
+    insert_payee(&conn, "Amazon")?;
+    let id = get_payees(&conn)?[0].id;
+    add_payee_pattern(&conn, id, "amazon*")?;
+
+    assert_eq!(apply_payee_patterns(&conn)?, 1);
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![id], vec![])?;
+    assert_eq!(page.transactions.len(), 1);
+    assert_eq!(page.transactions[0].payee_id, Some(id));
+    Ok(())
+}
+
+#[test]
+fn delete_should_keep_transactions() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_payee(&conn, "Amazon")?;
+    let id = get_payees(&conn)?[0].id;
+    crate::transaction_service::insert_transaction(&conn, -1.0, "amazon", None, "2023-11-01", None, vec![])?;
+
+    delete_payee(&conn, id)?;
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions.len(), 1);
+    assert_eq!(page.transactions[0].payee_id, None);
+    Ok(())
+}
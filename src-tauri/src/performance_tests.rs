@@ -0,0 +1,86 @@
+// Latency budgets of the most used queries on a large database. The tests are ignored by default
+// as generating the database takes a while, run them in release mode with
+// npm run test:performance
+use crate::migration_service::init_db_in_memory;
+use crate::models::ReportType;
+use rusqlite::Connection;
+use std::time::{Duration, Instant};
+
+const TRANSACTION_COUNT: i32 = 100_000;
+const CATEGORY_COUNT: i32 = 50;
+
+// Transactions are spread over five years and three accounts, most of them have a category
+fn generate_database() -> Result<Connection, rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    conn.execute_batch(&format!(
+        "
+        BEGIN;
+        INSERT INTO accounts (label, opening_balance) VALUES ('checking', 1000), ('savings', 5000), ('card', 0);
+
+        WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {categories})
+        INSERT INTO categories (label, sort_order) SELECT 'category ' || i, i FROM n;
+
+        WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < {transactions})
+        INSERT INTO transactions (value, name, description, date_created, account_id, created_at, updated_at)
+        SELECT
+        CASE WHEN i % 10 = 0 THEN 2500.0 ELSE -((i * 37) % 20000) / 100.0 END,
+        'payee ' || (i % 500),
+        CASE WHEN i % 3 = 0 THEN 'description ' || i END,
+        date('2019-01-01', '+' || (i % 1826) || ' days'),
+        1 + i % 3,
+        CURRENT_TIMESTAMP,
+        CURRENT_TIMESTAMP
+        FROM n;
+
+        INSERT INTO transaction_categories (transaction_id, category_id)
+        SELECT id, 1 + id % {categories} FROM transactions WHERE id % 7 != 0;
+        INSERT INTO transaction_categories (transaction_id, category_id)
+        SELECT id, 1 + (id / 3) % {categories} FROM transactions WHERE id % 5 = 0 AND (id / 3) % {categories} != id % {categories};
+        COMMIT;
+        ",
+        categories = CATEGORY_COUNT,
+        transactions = TRANSACTION_COUNT,
+    ))?;
+    Ok(conn)
+}
+
+// Fastest of a few runs so that a single hiccup of the machine does not fail the test
+fn fastest<T>(mut operation: impl FnMut() -> Result<T, rusqlite::Error>) -> Result<Duration, rusqlite::Error> {
+    let mut fastest = Duration::MAX;
+    for _ in 0..3 {
+        let start = Instant::now();
+        operation()?;
+        fastest = fastest.min(start.elapsed());
+    }
+    Ok(fastest)
+}
+
+#[test]
+#[ignore]
+fn query_page_should_be_within_budget() -> Result<(), rusqlite::Error> {
+    let conn = generate_database()?;
+
+    let first_page = fastest(|| {
+        crate::transaction_service::query_page(&conn, 15, 1, "", vec![], vec![], vec![])
+    })?;
+    assert!(first_page < Duration::from_millis(250), "First page took {:?}", first_page);
+
+    let filtered_page = fastest(|| {
+        crate::transaction_service::query_page(&conn, 15, 20, "payee 1", vec![1, 2], vec![], vec![])
+    })?;
+    assert!(filtered_page < Duration::from_millis(250), "Filtered page took {:?}", filtered_page);
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn get_basic_report_should_be_within_budget() -> Result<(), rusqlite::Error> {
+    let conn = generate_database()?;
+
+    let month = fastest(|| crate::report_service::get_basic_report(&conn, ReportType::MONTH, "2021-06-15"))?;
+    assert!(month < Duration::from_millis(100), "Month report took {:?}", month);
+
+    let year = fastest(|| crate::report_service::get_basic_report(&conn, ReportType::YEAR, "2021-06-15"))?;
+    assert!(year < Duration::from_millis(250), "Year report took {:?}", year);
+    Ok(())
+}
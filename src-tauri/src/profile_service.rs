@@ -0,0 +1,182 @@
+use crate::attachment_service;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+// Database used before profiles existed, it becomes the default profile
+const LEGACY_DATABASE: &str = "finance-app.sqlite";
+const ACTIVE_PROFILE_FILE: &str = "active_profile";
+
+fn profiles_dir(app_dir: &Path) -> PathBuf {
+    app_dir.join("profiles")
+}
+
+// Profile names are used as file names
+pub fn validate_name(name: &str) -> Result<String, Box<dyn Error>> {
+    let name = name.trim();
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid profile name: {:?}", name).into());
+    }
+    Ok(name.to_string())
+}
+
+// Each profile is a database file, attachments are stored next to it
+pub fn profile_path(app_dir: &Path, name: &str) -> PathBuf {
+    profiles_dir(app_dir).join(format!("{}.sqlite", name))
+}
+
+// Creates the profile directory, the database of the app before profiles existed is moved
+// to the default profile. Attachment directories of older versions are renamed, a profile
+// does not share its directory with another database.
+pub fn init_profiles(app_dir: &Path) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(profiles_dir(app_dir))?;
+
+    let legacy = app_dir.join(LEGACY_DATABASE);
+    let default = profile_path(app_dir, DEFAULT_PROFILE);
+    if legacy.exists() && !default.exists() {
+        fs::rename(&legacy, &default)?;
+        move_attachments(&attachment_service::legacy_attachments_dir(&legacy), &default)?;
+    }
+
+    for name in get_profiles(app_dir)? {
+        let path = profile_path(app_dir, &name);
+        move_attachments(&attachment_service::legacy_attachments_dir(&path), &path)?;
+    }
+    Ok(())
+}
+
+fn move_attachments(dir: &Path, database: &Path) -> Result<(), Box<dyn Error>> {
+    let new_dir = attachment_service::attachments_dir(database);
+    if dir.exists() && !new_dir.exists() {
+        fs::rename(dir, new_dir)?;
+    }
+    Ok(())
+}
+
+pub fn get_profiles(app_dir: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let dir = profiles_dir(app_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut profiles = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "sqlite") {
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                profiles.push(name.to_string());
+            }
+        }
+    }
+    profiles.sort();
+    Ok(profiles)
+}
+
+// The active profile is remembered between starts, the default profile is used at first
+pub fn get_active_profile(app_dir: &Path) -> String {
+    fs::read_to_string(app_dir.join(ACTIVE_PROFILE_FILE))
+        .ok()
+        .and_then(|name| validate_name(&name).ok())
+        .unwrap_or(DEFAULT_PROFILE.to_string())
+}
+
+pub fn set_active_profile(app_dir: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+    let name = validate_name(name)?;
+    fs::create_dir_all(app_dir)?;
+    fs::write(app_dir.join(ACTIVE_PROFILE_FILE), name)?;
+    Ok(())
+}
+
+// Creates an empty database for the profile
+pub fn create_profile(app_dir: &Path, name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let name = validate_name(name)?;
+    let path = profile_path(app_dir, &name);
+    if path.exists() {
+        return Err(format!("Profile {:?} already exists", name).into());
+    }
+
+    fs::create_dir_all(profiles_dir(app_dir))?;
+    let db = crate::encryption_service::open_database(&path, None)?;
+    crate::migration_service::init_tables(&db)?;
+    Ok(path)
+}
+
+// The database of the profile must not be open while it is renamed
+pub fn rename_profile(app_dir: &Path, name: &str, new_name: &str) -> Result<(), Box<dyn Error>> {
+    let path = profile_path(app_dir, &validate_name(name)?);
+    let new_name = validate_name(new_name)?;
+    let new_path = profile_path(app_dir, &new_name);
+    if !path.exists() {
+        return Err(format!("Profile {:?} does not exist", name).into());
+    }
+    if new_path.exists() {
+        return Err(format!("Profile {:?} already exists", new_name).into());
+    }
+
+    fs::rename(&path, &new_path)?;
+    move_attachments(&attachment_service::attachments_dir(&path), &new_path)?;
+    if get_active_profile(app_dir) == name.trim() {
+        set_active_profile(app_dir, &new_name)?;
+    }
+    Ok(())
+}
+
+// Removes the database of the profile together with its attachments
+pub fn delete_profile(app_dir: &Path, name: &str) -> Result<(), Box<dyn Error>> {
+    let name = validate_name(name)?;
+    if get_active_profile(app_dir) == name {
+        return Err("The active profile cannot be deleted".into());
+    }
+
+    let path = profile_path(app_dir, &name);
+    if !path.exists() {
+        return Err(format!("Profile {:?} does not exist", name).into());
+    }
+    fs::remove_file(&path)?;
+    if attachment_service::attachments_dir(&path).exists() {
+        fs::remove_dir_all(attachment_service::attachments_dir(&path))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+use crate::shared_service::TestDir;
+
+#[test]
+fn legacy_database_should_become_default_profile() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("legacy_database_should_become_default_profile");
+    fs::write(dir.join(LEGACY_DATABASE), b"")?;
+    fs::create_dir_all(dir.join("finance-app.attachments"))?;
+
+    init_profiles(&dir)?;
+    assert_eq!(get_profiles(&dir)?, vec![DEFAULT_PROFILE]);
+    assert!(!dir.join(LEGACY_DATABASE).exists());
+    assert!(dir.join("profiles").join("default.sqlite.attachments").exists());
+    assert_eq!(get_active_profile(&dir), DEFAULT_PROFILE);
+    Ok(())
+}
+
+#[test]
+fn profiles_should_be_created_renamed_and_deleted() -> Result<(), Box<dyn Error>> {
+    let dir = TestDir::new("profiles_should_be_created_renamed_and_deleted");
+    create_profile(&dir, "personal")?;
+    create_profile(&dir, "business")?;
+    assert!(create_profile(&dir, "business").is_err());
+    assert!(create_profile(&dir, "../business").is_err());
+    assert_eq!(get_profiles(&dir)?, vec!["business", "personal"]);
+
+    set_active_profile(&dir, "business")?;
+    rename_profile(&dir, "business", "company")?;
+    assert_eq!(get_active_profile(&dir), "company");
+    assert!(delete_profile(&dir, "company").is_err());
+
+    delete_profile(&dir, "personal")?;
+    assert_eq!(get_profiles(&dir)?, vec!["company"]);
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use crate::models::{Frequency, RecurringTransaction};
+use crate::shared_service::parse_date;
+use chrono::{Days, Months, NaiveDate};
+use rusqlite::{named_params, Connection};
+
+fn frequency_to_str(frequency: Frequency) -> &'static str {
+    match frequency {
+        Frequency::WEEKLY => "WEEKLY",
+        Frequency::MONTHLY => "MONTHLY",
+        Frequency::YEARLY => "YEARLY",
+    }
+}
+
+fn frequency_from_str(frequency: &str) -> Frequency {
+    match frequency {
+        "WEEKLY" => Frequency::WEEKLY,
+        "YEARLY" => Frequency::YEARLY,
+        _ => Frequency::MONTHLY,
+    }
+}
+
+pub fn insert_recurring_transaction(
+    db: &Connection,
+    value: f64,
+    name: &str,
+    account_id: Option<i32>,
+    frequency: Frequency,
+    start_date: &str,
+    end_date: Option<&str>,
+) -> Result<(), rusqlite::Error> {
+    let start_date = parse_date(start_date)?;
+    let end_date = end_date.map(parse_date).transpose()?;
+
+    db.execute(
+        "
+        INSERT INTO recurring_transactions(
+            value,
+            name,
+            account_id,
+            frequency,
+            start_date,
+            end_date
+        )
+        VALUES(
+            :value,
+            :name,
+            :account_id,
+            :frequency,
+            :start_date,
+            :end_date
+        );
+        ",
+        named_params! {
+            ":value": value,
+            ":name": name,
+            ":account_id": account_id,
+            ":frequency": frequency_to_str(frequency),
+            ":start_date": start_date.format("%Y-%m-%d").to_string(),
+            ":end_date": end_date.map(|d| d.format("%Y-%m-%d").to_string()),
+        },
+    )?;
+    Ok(())
+}
+
+pub fn delete_recurring_transaction(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "DELETE FROM recurring_transactions WHERE id = (:id);",
+        named_params! {
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn get_recurring_transactions(
+    db: &Connection,
+) -> Result<Vec<RecurringTransaction>, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "
+        SELECT id, value, name, account_id, frequency, start_date, end_date
+        FROM recurring_transactions;
+        ",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut recurring = Vec::new();
+    while let Some(row) = rows.next()? {
+        let frequency: String = row.get(4)?;
+        recurring.push(RecurringTransaction {
+            id: row.get(0)?,
+            value: row.get(1)?,
+            name: row.get(2)?,
+            account_id: row.get(3)?,
+            frequency: frequency_from_str(&frequency),
+            start_date: row.get(5)?,
+            end_date: row.get(6)?,
+        });
+    }
+    Ok(recurring)
+}
+
+// Dates on which the recurring transaction occurs between the given dates, both inclusive.
+// Every occurrence is counted from the start date so that a monthly item on the 31st
+// falls on the last day of shorter months without drifting.
+pub fn get_occurrences(
+    recurring: &RecurringTransaction,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<NaiveDate>, rusqlite::Error> {
+    let start = parse_date(&recurring.start_date)?;
+    let end = match &recurring.end_date {
+        Some(end_date) => parse_date(end_date)?.min(to),
+        None => to,
+    };
+
+    let mut occurrences = Vec::new();
+    for index in 0.. {
+        let occurrence = match recurring.frequency {
+            Frequency::WEEKLY => start.checked_add_days(Days::new(index * 7)),
+            Frequency::MONTHLY => start.checked_add_months(Months::new(index as u32)),
+            Frequency::YEARLY => start.checked_add_months(Months::new(index as u32 * 12)),
+        };
+        match occurrence {
+            Some(date) if date <= end => {
+                if date >= from {
+                    occurrences.push(date);
+                }
+            }
+            _ => break,
+        }
+    }
+    Ok(occurrences)
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn after_insert_should_be_readable() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_recurring_transaction(&conn, -500.0, "rent", None, Frequency::MONTHLY, "2023-11-01", None)?;
+    let list = get_recurring_transactions(&conn)?;
+
+    assert!(list.len() == 1, "Expected list with one item, got {:?}", list);
+    assert_eq!(list[0].frequency, Frequency::MONTHLY);
+    assert_eq!(list[0].start_date, "2023-11-01");
+    assert_eq!(list[0].end_date, None);
+    Ok(())
+}
+
+#[test]
+fn insert_should_fail_for_invalid_date() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    let result = insert_recurring_transaction(&conn, -500.0, "rent", None, Frequency::MONTHLY, "11/2023", None);
+
+    assert!(result.is_err());
+    assert!(get_recurring_transactions(&conn)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn delete_should_remove_entry() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_recurring_transaction(&conn, -500.0, "rent", None, Frequency::MONTHLY, "2023-11-01", None)?;
+    let id = get_recurring_transactions(&conn)?[0].id;
+
+    delete_recurring_transaction(&conn, id)?;
+
+    assert!(get_recurring_transactions(&conn)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn monthly_occurrences_should_not_drift() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_recurring_transaction(&conn, 2000.0, "salary", None, Frequency::MONTHLY, "2023-01-31", Some("2023-04-30"))?;
+    let recurring = &get_recurring_transactions(&conn)?[0];
+
+    let occurrences = get_occurrences(
+        recurring,
+        parse_date("2023-02-01")?,
+        parse_date("2023-12-31")?,
+    )?;
+
+    assert_eq!(
+        occurrences,
+        vec![
+            parse_date("2023-02-28")?,
+            parse_date("2023-03-31")?,
+            parse_date("2023-04-30")?
+        ]
+    );
+    Ok(())
+}
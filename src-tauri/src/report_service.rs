@@ -1,5 +1,10 @@
-use crate::models::{BasicReport, ReportType};
-use chrono::NaiveDate;
+use crate::models::{
+    AccountBalanceHistory, BalanceInterval, BasicReport, CategoryDelta, ComparisonPeriod,
+    CategoryTrend, ComparisonReport, DateField, Forecast, ForecastPoint, NetWorthMonth, PayeeTotal,
+    ReportType, TagTotal, TrendReport,
+};
+use crate::shared_service::{end_of_day, format_date, invalid_date, invalid_input, parse_date};
+use chrono::{Datelike, Months, NaiveDate};
 use rusqlite::{named_params, Connection};
 use std::collections::HashMap;
 
@@ -7,48 +12,50 @@ pub fn get_supported_report_types() -> Result<Vec<ReportType>, ()> {
     Ok(vec![ReportType::MONTH, ReportType::YEAR])
 }
 
-pub fn get_days_from_month(date: &str) -> i64 {
-    let date_parts: Vec<&str> = date.split("-").collect();
-    let year: i32 = date_parts[0].parse().unwrap();
-    let month: u32 = date_parts[1].parse().unwrap();
-
-    NaiveDate::from_ymd(
-        match month {
-            12 => year + 1,
-            _ => year,
-        },
-        match month {
-            12 => 1,
-            _ => month + 1,
-        },
-        1,
-    )
-    .signed_duration_since(NaiveDate::from_ymd(year, month, 1))
-    .num_days()
+// First day of the period of the selected date and the first day after it
+fn report_range(
+    selected_date: &str,
+    report_type: ReportType,
+) -> Result<(NaiveDate, NaiveDate), rusqlite::Error> {
+    let date = parse_date(selected_date)?;
+    let (start, months) = match report_type {
+        ReportType::YEAR => (date.with_ordinal(1), 12),
+        ReportType::MONTH => (date.with_day(1), 1),
+    };
+    let start = start.ok_or(invalid_date(selected_date))?;
+    let end = start
+        .checked_add_months(Months::new(months))
+        .ok_or(invalid_date(selected_date))?;
+    Ok((start, end))
 }
 
-fn date_selector(selected_date: &str, report_type: ReportType) -> Result<String, rusqlite::Error> {
-    // expected date is of format YYYY-MM-DD
-    let date_parts: Vec<&str> = selected_date.split("-").collect();
-
-    match (date_parts.as_slice(), report_type) {
-        ([year, _, _], ReportType::YEAR) => Ok(format!("{}-__-__", year)),
-        ([year, month, _], ReportType::MONTH) => Ok(format!("{}-{}-__", year, month)),
-        _ => rusqlite::Result::Err(rusqlite::Error::InvalidQuery),
+// SQL expression of the date a transaction is bucketed by
+fn date_column(date_field: DateField) -> &'static str {
+    match date_field {
+        DateField::DATE_CREATED => "t.date_created",
+        DateField::BOOKING_DATE => "COALESCE(t.booking_date, t.date_created)",
+        DateField::VALUE_DATE => "COALESCE(t.value_date, t.date_created)",
     }
 }
 
-fn get_dates_totals(db: &Connection, date: &str) -> Result<HashMap<String, f64>, rusqlite::Error> {
-    let mut date_statement = db.prepare(
+fn get_dates_totals(
+    db: &Connection,
+    (start, end): (NaiveDate, NaiveDate),
+    date_field: DateField,
+) -> Result<HashMap<String, f64>, rusqlite::Error> {
+    let mut date_statement = db.prepare(&format!(
         "
-        SELECT SUM(t.value), t.date_created FROM transactions t
-        WHERE t.date_created LIKE (:date)
-        GROUP BY t.date_created
+        SELECT SUM(t.value), date({date}) as day FROM transactions t
+        WHERE {date} >= (:start_date) AND {date} < (:end_date)
+        AND t.deleted_at IS NULL
+        GROUP BY day
         ",
-    )?;
+        date = date_column(date_field)
+    ))?;
 
     let mut date_rows = date_statement.query(named_params! {
-        ":date": date,
+        ":start_date": format_date(start),
+        ":end_date": format_date(end),
     })?;
 
     let mut dates: HashMap<String, f64> = HashMap::new();
@@ -62,96 +69,140 @@ fn get_dates_totals(db: &Connection, date: &str) -> Result<HashMap<String, f64>,
     Ok(dates)
 }
 
-fn query_category_rows(
-    db: &Connection,
-    stmt: &str,
-    date: &str,
-) -> Result<HashMap<String, f64>, rusqlite::Error> {
-    let mut categories_statement = db.prepare(stmt)?;
-
-    let mut categories_rows = categories_statement.query(named_params! {
-        ":date": date,
-    })?;
-
-    let mut categories: HashMap<String, f64> = HashMap::new();
-    while let Some(row) = categories_rows.next()? {
-        let labels: String = row.get(0)?;
-        let sum: f64 = row.get(1)?;
-        categories.insert(labels, sum);
-    }
-
-    Ok(categories)
+// Category sums of the period split into income and expenses, with the color of each group.
+// Transactions are grouped by the combination of their categories, the labels and ids of a
+// group are in the order of the categories.
+struct CategoryGroups {
+    income: HashMap<String, f64>,
+    expenses: HashMap<String, f64>,
+    colors: HashMap<String, String>,
 }
 
-fn get_category_income(
+// A transaction counts as income or expense by the kind of its categories, e.g. a refund of an
+// expense category lowers the expenses. Categories of both kinds, or of mixed kinds, go by
+// the sign of the value.
+fn get_category_groups(
     db: &Connection,
-    date: &str,
-) -> Result<HashMap<String, f64>, rusqlite::Error> {
-    let categories = query_category_rows(
-        db,
-        "
-        WITH grouped_category AS (
-            SELECT 
-              tc.transaction_id as transaction_id,
-              group_concat(c.id, ', ') as ids,
-              group_concat(c.label, ', ') as labels
-            FROM categories c
-            INNER JOIN transaction_categories tc ON tc.category_id = c.id
-            GROUP BY tc.transaction_id
-        )
-
-        SELECT gc.labels, SUM(t.value)
-        FROM transactions t
-        INNER JOIN grouped_category gc ON gc.transaction_id = t.id
-        WHERE t.date_created LIKE (:date) AND t.value > 0
-        GROUP BY t.date_created, gc.labels
-        ",
-        date,
-    )?;
-    Ok(categories)
+    range: (NaiveDate, NaiveDate),
+    date_field: DateField,
+) -> Result<CategoryGroups, rusqlite::Error> {
+    get_category_groups_where(db, range, date_field, "", &[])
 }
 
-fn get_category_expense(
+// Category groups of only the transactions matching the additional conditions
+fn get_category_groups_where(
     db: &Connection,
-    date: &str,
-) -> Result<HashMap<String, f64>, rusqlite::Error> {
-    let categories = query_category_rows(
-        db,
+    (start, end): (NaiveDate, NaiveDate),
+    date_field: DateField,
+    conditions: &str,
+    params: &[(&str, &dyn rusqlite::ToSql)],
+) -> Result<CategoryGroups, rusqlite::Error> {
+    let mut statement = db.prepare(&format!(
         "
-        WITH grouped_category AS (
-            SELECT 
-              tc.transaction_id as transaction_id,
-              group_concat(c.id, ', ') as ids,
-              group_concat(c.label, ', ') as labels
-            FROM categories c
-            INNER JOIN transaction_categories tc ON tc.category_id = c.id
-            GROUP BY tc.transaction_id
+        -- only the categories of transactions in the period are grouped, the cross join makes
+        -- sqlite find the transactions by date first
+        WITH category_links AS (
+            SELECT
+              t.id as transaction_id,
+              t.value as value,
+              c.id as category_id,
+              COALESCE(c.display_label, c.label) as label,
+              c.kind
+            FROM transactions t
+            CROSS JOIN transaction_categories tc ON tc.transaction_id = t.id
+            INNER JOIN categories c ON c.id = tc.category_id
+            WHERE {date} >= (:start_date) AND {date} < (:end_date)
+            AND t.deleted_at IS NULL AND c.deleted_at IS NULL {conditions}
+            ORDER BY t.id, c.sort_order, c.id
+        ),
+        grouped_category AS (
+            SELECT
+              transaction_id,
+              value,
+              group_concat(label, ', ') as labels,
+              group_concat(category_id) as category_ids,
+              MAX(kind = 'INCOME') as has_income,
+              MAX(kind = 'EXPENSE') as has_expense
+            FROM category_links
+            GROUP BY transaction_id
         )
 
-        SELECT gc.labels, SUM(t.value)
-        FROM transactions t
-        INNER JOIN grouped_category gc ON gc.transaction_id = t.id
-        WHERE t.date_created LIKE (:date) AND t.value <= 0
-        GROUP BY t.date_created, gc.labels
+        SELECT
+        gc.labels,
+        gc.category_ids,
+        CASE
+            WHEN gc.has_income AND NOT gc.has_expense THEN 1
+            WHEN gc.has_expense AND NOT gc.has_income THEN 0
+            ELSE gc.value > 0
+        END as is_income,
+        SUM(gc.value)
+        FROM grouped_category gc
+        GROUP BY gc.labels, is_income
         ",
-        date,
-    )?;
-    Ok(categories)
+        date = date_column(date_field)
+    ))?;
+
+    let start_date = format_date(start);
+    let end_date = format_date(end);
+    let mut all_params: Vec<(&str, &dyn rusqlite::ToSql)> =
+        vec![(":start_date", &start_date), (":end_date", &end_date)];
+    all_params.extend_from_slice(params);
+    let mut rows = statement.query(all_params.as_slice())?;
+
+    // archived categories keep their color in reports
+    let category_colors: HashMap<i32, String> = crate::category_service::query_categories(db, true)?
+        .into_iter()
+        .map(|category| (category.id, category.color))
+        .collect();
+
+    let mut groups = CategoryGroups {
+        income: HashMap::new(),
+        expenses: HashMap::new(),
+        colors: HashMap::new(),
+    };
+    while let Some(row) = rows.next()? {
+        let labels: String = row.get(0)?;
+        let category_ids: String = row.get(1)?;
+        let is_income: bool = row.get(2)?;
+        let sum: f64 = row.get(3)?;
+
+        // a group of several categories uses the color of its first category
+        let first_id = category_ids.split(',').next().and_then(|id| id.parse::<i32>().ok());
+        if let Some(color) = first_id.and_then(|id| category_colors.get(&id)) {
+            groups.colors.insert(labels.to_string(), color.to_string());
+        }
+        if is_income {
+            groups.income.insert(labels, sum);
+        } else {
+            groups.expenses.insert(labels, sum);
+        }
+    }
+
+    Ok(groups)
 }
 
-fn get_uncategorized_total(db: &Connection, date: &str) -> Result<f64, rusqlite::Error> {
-    let mut uncategorized_statement = db.prepare(
+fn get_uncategorized_total(
+    db: &Connection,
+    (start, end): (NaiveDate, NaiveDate),
+    date_field: DateField,
+) -> Result<f64, rusqlite::Error> {
+    let mut uncategorized_statement = db.prepare(&format!(
         "
         SELECT COALESCE(SUM(CAST(t.value AS REAL)), 0) as result
         FROM transactions t
-        WHERE t.date_created LIKE (:date) AND t.id NOT IN (
-            SELECT tc.transaction_id FROM transaction_categories tc
+        WHERE {date} >= (:start_date) AND {date} < (:end_date)
+        AND t.deleted_at IS NULL AND NOT EXISTS (
+            SELECT 1 FROM transaction_categories tc
+            INNER JOIN categories c ON c.id = tc.category_id
+            WHERE tc.transaction_id = t.id AND c.deleted_at IS NULL
         )
         ",
-    )?;
+        date = date_column(date_field)
+    ))?;
 
     let mut uncategorized_rows = uncategorized_statement.query(named_params! {
-        ":date": date,
+        ":start_date": format_date(start),
+        ":end_date": format_date(end),
     })?;
 
     let mut uncategorized = 0.0;
@@ -161,89 +212,678 @@ fn get_uncategorized_total(db: &Connection, date: &str) -> Result<f64, rusqlite:
     Ok(uncategorized)
 }
 
+#[cfg(test)]
 pub fn get_basic_report(
     db: &Connection,
     report_type: ReportType,
     selected_date: &str,
 ) -> Result<BasicReport, rusqlite::Error> {
-    let date = date_selector(selected_date, report_type)?;
-    let raw_dates = get_dates_totals(db, &date)?;
+    get_basic_report_by(db, report_type, selected_date, DateField::DATE_CREATED)
+}
 
-    let mut total: f64 = 0.0;
-    for (_, sum) in &raw_dates {
-        total += sum;
-    }
+// Basic report where transactions are placed in the period by the chosen date
+pub fn get_basic_report_by(
+    db: &Connection,
+    report_type: ReportType,
+    selected_date: &str,
+    date_field: DateField,
+) -> Result<BasicReport, rusqlite::Error> {
+    let range = report_range(selected_date, report_type)?;
+    let (start, end) = range;
+    let raw_dates = get_dates_totals(db, range, date_field)?;
+
+    let total: f64 = raw_dates.values().sum();
 
     let dates = match report_type {
         ReportType::YEAR => {
-            let mut iter = raw_dates.iter();
             let mut grouped: HashMap<String, f64> = HashMap::new();
-            let year = date.split("-").take(1).collect::<Vec<&str>>()[0];
-            for i in 0..12 {
-                grouped.insert(format!("{}-{:02}", year, i + 1), 0.0);
+            for month in 0..12 {
+                grouped.insert(format!("{}-{:02}", start.year(), month + 1), 0.0);
             }
-            while let Some((date, sum)) = iter.next() {
-                let month = date.split("-").take(2).collect::<Vec<&str>>().join("-");
-                grouped.get_mut(&month).map(|v| *v += *sum);
+            for (date, sum) in &raw_dates {
+                // keys are YYYY-MM-DD, the month is the first 7 characters
+                if let Some(value) = grouped.get_mut(&date[..7]) {
+                    *value += *sum;
+                }
             }
             grouped
         }
         ReportType::MONTH => {
-            let mut grouped: HashMap<String, f64> = HashMap::new();
-            let month = date.split("-").take(2).collect::<Vec<&str>>().join("-");
-            let days = get_days_from_month(&date);
-            for d in 0..days {
-                grouped.insert(format!("{}-{:02}", month, d + 1), 0.0);
-            }
-            let mut iter = raw_dates.iter();
-            while let Some((date, sum)) = iter.next() {
-                // This is fine in this case in comparison to month, the aggeration is done in the
-                // db query
-                grouped.insert(date.to_string(), *sum);
-            }
+            let mut grouped: HashMap<String, f64> = start
+                .iter_days()
+                .take_while(|day| *day < end)
+                .map(|day| (format_date(day), 0.0))
+                .collect();
+            // This is fine in this case in comparison to year, the aggregation is done in the
+            // db query
+            grouped.extend(raw_dates);
             grouped
         }
     };
 
+    let category_groups = get_category_groups(db, range, date_field)?;
+
     let report = BasicReport {
         total,
-        uncategorized: get_uncategorized_total(db, &date)?,
+        uncategorized: get_uncategorized_total(db, range, date_field)?,
         dates,
-        category_income: get_category_income(db, &date)?,
-        category_expenses: get_category_expense(db, &date)?,
+        category_income: category_groups.income,
+        category_expenses: category_groups.expenses,
+        category_colors: category_groups.colors,
     };
 
     Ok(report)
 }
 
+// Payees with the largest absolute totals in the period of the selected date
+pub fn get_top_payees(
+    db: &Connection,
+    report_type: ReportType,
+    selected_date: &str,
+    limit: i32,
+    date_field: DateField,
+) -> Result<Vec<PayeeTotal>, rusqlite::Error> {
+    let (start, end) = report_range(selected_date, report_type)?;
+
+    let mut statement = db.prepare(&format!(
+        "
+        SELECT p.id, p.label, SUM(t.value) as total, COUNT(t.id)
+        FROM transactions t
+        INNER JOIN payees p ON p.id = t.payee_id
+        WHERE {date} >= (:start_date) AND {date} < (:end_date)
+        AND t.deleted_at IS NULL
+        GROUP BY p.id
+        ORDER BY ABS(total) DESC
+        LIMIT (:limit)
+        ",
+        date = date_column(date_field)
+    ))?;
+
+    let mut rows = statement.query(named_params! {
+        ":start_date": format_date(start),
+        ":end_date": format_date(end),
+        ":limit": limit,
+    })?;
+
+    let mut payees = Vec::new();
+    while let Some(row) = rows.next()? {
+        payees.push(PayeeTotal {
+            payee_id: row.get(0)?,
+            label: row.get(1)?,
+            total: row.get(2)?,
+            count: row.get(3)?,
+        });
+    }
+
+    Ok(payees)
+}
+
+// Totals per tag between the given dates, tags are independent of the categories so a
+// transaction is counted under each of its tags
+pub fn get_tag_report(
+    db: &Connection,
+    start_date: &str,
+    end_date: &str,
+    date_field: DateField,
+) -> Result<Vec<TagTotal>, rusqlite::Error> {
+    let start = parse_date(start_date)?;
+
+    let mut statement = db.prepare(&format!(
+        "
+        SELECT tg.id, tg.label, SUM(t.value), COUNT(t.id)
+        FROM transactions t
+        INNER JOIN transaction_tags tt ON tt.transaction_id = t.id
+        INNER JOIN tags tg ON tg.id = tt.tag_id
+        WHERE {date} >= (:start_date) AND {date} < (:end_date)
+        AND t.deleted_at IS NULL
+        GROUP BY tg.id
+        ORDER BY tg.label
+        ",
+        date = date_column(date_field)
+    ))?;
+
+    let mut rows = statement.query(named_params! {
+        ":start_date": format_date(start),
+        ":end_date": end_of_day(end_date)?,
+    })?;
+
+    let mut tags = Vec::new();
+    while let Some(row) = rows.next()? {
+        tags.push(TagTotal {
+            tag_id: row.get(0)?,
+            label: row.get(1)?,
+            total: row.get(2)?,
+            count: row.get(3)?,
+        });
+    }
+
+    Ok(tags)
+}
+
+fn delta(label: &str, current: f64, previous: f64) -> CategoryDelta {
+    CategoryDelta {
+        label: label.to_string(),
+        current,
+        previous,
+        delta: current - previous,
+        percentage: if previous == 0.0 {
+            None
+        } else {
+            Some((current - previous) / previous.abs() * 100.0)
+        },
+    }
+}
+
+fn category_totals(report: &BasicReport) -> HashMap<String, f64> {
+    let mut totals: HashMap<String, f64> = HashMap::new();
+    for (labels, sum) in report.category_income.iter().chain(report.category_expenses.iter()) {
+        *totals.entry(labels.to_string()).or_default() += sum;
+    }
+    totals
+}
+
+// Compares the period of the selected date against an earlier period of the same report type,
+// categories are compared by their net sum of income and expenses
+pub fn get_comparison_report(
+    db: &Connection,
+    report_type: ReportType,
+    selected_date: &str,
+    period: ComparisonPeriod,
+    compare_date: Option<&str>,
+    date_field: DateField,
+) -> Result<ComparisonReport, rusqlite::Error> {
+    let date = parse_date(selected_date)?;
+    let previous_date = match (period, report_type) {
+        (ComparisonPeriod::PREVIOUS_PERIOD, ReportType::MONTH) => {
+            date.checked_sub_months(Months::new(1))
+        }
+        (ComparisonPeriod::PREVIOUS_PERIOD, ReportType::YEAR)
+        | (ComparisonPeriod::SAME_PERIOD_LAST_YEAR, _) => date.checked_sub_months(Months::new(12)),
+        (ComparisonPeriod::CUSTOM, _) => {
+            let compare_date =
+                compare_date.ok_or(invalid_input("a custom comparison needs a compare date"))?;
+            Some(parse_date(compare_date)?)
+        }
+    }
+    .ok_or(invalid_date(selected_date))?;
+
+    let current_date = format_date(date);
+    let previous_date = format_date(previous_date);
+
+    let current = get_basic_report_by(db, report_type, &current_date, date_field)?;
+    let previous = get_basic_report_by(db, report_type, &previous_date, date_field)?;
+
+    let current_categories = category_totals(&current);
+    let previous_categories = category_totals(&previous);
+
+    let mut labels: Vec<&String> = current_categories
+        .keys()
+        .chain(previous_categories.keys())
+        .collect();
+    labels.sort();
+    labels.dedup();
+
+    let mut categories = Vec::new();
+    let mut appeared = Vec::new();
+    let mut vanished = Vec::new();
+    for label in labels {
+        match (current_categories.get(label), previous_categories.get(label)) {
+            (Some(_), None) => appeared.push(label.to_string()),
+            (None, Some(_)) => vanished.push(label.to_string()),
+            _ => {}
+        }
+        categories.push(delta(
+            label,
+            *current_categories.get(label).unwrap_or(&0.0),
+            *previous_categories.get(label).unwrap_or(&0.0),
+        ));
+    }
+
+    Ok(ComparisonReport {
+        current_date,
+        previous_date,
+        total: delta("total", current.total, previous.total),
+        categories,
+        appeared,
+        vanished,
+    })
+}
+
+// Least squares slope of the values against their index, i.e. the change per month
+fn trend_slope(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<f64>() / n;
+
+    let mut numerator = 0.0;
+    let mut denominator = 0.0;
+    for (x, y) in values.iter().enumerate() {
+        numerator += (x as f64 - mean_x) * (y - mean_y);
+        denominator += (x as f64 - mean_x).powi(2);
+    }
+    numerator / denominator
+}
+
+// Monthly totals per category for the given number of months ending with the month of the
+// selected date. Unlike the basic report each category is counted separately, so a transaction
+// with two categories is included in the totals of both.
+pub fn get_trend_report(
+    db: &Connection,
+    selected_date: &str,
+    months: u32,
+    date_field: DateField,
+) -> Result<TrendReport, rusqlite::Error> {
+    let date = parse_date(selected_date)?;
+    if months < 1 {
+        return Err(invalid_input("months must be at least 1"));
+    }
+    let end = last_day_of_month(date);
+    let start = end
+        .with_day(1)
+        .and_then(|d| d.checked_sub_months(Months::new(months - 1)))
+        .ok_or(invalid_date(selected_date))?;
+
+    let month_keys: Vec<String> = (0..months)
+        .filter_map(|month| start.checked_add_months(Months::new(month)))
+        .map(|month| month.format("%Y-%m").to_string())
+        .collect();
+
+    let mut statement = db.prepare(&format!(
+        "
+        SELECT c.id, COALESCE(c.display_label, c.label), strftime('%Y-%m', {date}) as month, SUM(t.value)
+        FROM transactions t
+        INNER JOIN transaction_categories tc ON tc.transaction_id = t.id
+        INNER JOIN categories c ON c.id = tc.category_id
+        WHERE {date} >= (:start_date) AND {date} < (:end_date)
+        AND t.deleted_at IS NULL AND c.deleted_at IS NULL
+        GROUP BY c.id, month
+        ORDER BY c.label
+        ",
+        date = date_column(date_field)
+    ))?;
+
+    let mut rows = statement.query(named_params! {
+        ":start_date": format_date(start),
+        ":end_date": end_of_day(&format_date(end))?,
+    })?;
+
+    let mut categories: Vec<CategoryTrend> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: i32 = row.get(0)?;
+        let month: Option<String> = row.get(2)?;
+        let sum: f64 = row.get(3)?;
+
+        if categories.last().map(|c| c.id) != Some(id) {
+            categories.push(CategoryTrend {
+                id,
+                label: row.get(1)?,
+                months: month_keys.iter().map(|key| (key.to_string(), 0.0)).collect(),
+                average: 0.0,
+                min: 0.0,
+                max: 0.0,
+                slope: 0.0,
+            });
+        }
+        if let (Some(category), Some(month)) = (categories.last_mut(), month) {
+            category.months.insert(month, sum);
+        }
+    }
+
+    for category in categories.iter_mut() {
+        let values: Vec<f64> = month_keys
+            .iter()
+            .map(|key| *category.months.get(key).unwrap_or(&0.0))
+            .collect();
+        category.average = values.iter().sum::<f64>() / values.len() as f64;
+        category.min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        category.max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        category.slope = trend_slope(&values);
+    }
+
+    Ok(TrendReport {
+        months: month_keys,
+        categories,
+    })
+}
+
+type AccountSums = HashMap<(Option<i32>, String), f64>;
+
+fn query_account_sums(
+    db: &Connection,
+    stmt: &str,
+    params: &[(&str, &dyn rusqlite::ToSql)],
+) -> Result<AccountSums, rusqlite::Error> {
+    let mut statement = db.prepare(stmt)?;
+
+    let mut rows = statement.query(params)?;
+
+    let mut sums: AccountSums = HashMap::new();
+    while let Some(row) = rows.next()? {
+        let account_id: Option<i32> = row.get(0)?;
+        let date: String = row.get(1)?;
+        let sum: f64 = row.get(2)?;
+        sums.insert((account_id, date), sum);
+    }
+
+    Ok(sums)
+}
+
+// Balance of each account at the end of every day or month between the given dates,
+// transactions without an account are grouped under 'unassigned'
+pub fn get_balance_history(
+    db: &Connection,
+    interval: BalanceInterval,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<AccountBalanceHistory>, rusqlite::Error> {
+    let start = parse_date(start_date)?;
+    let end = parse_date(end_date)?;
+    if start > end {
+        return Err(invalid_input("start date must not be after end date"));
+    }
+    let start_date = format_date(start);
+
+    let mut accounts: Vec<(Option<i32>, String, f64)> = crate::account_service::get_accounts(db)?
+        .into_iter()
+        .map(|account| (Some(account.id), account.label, account.opening_balance))
+        .collect();
+
+    let unassigned: i32 = db.query_row(
+        "SELECT COUNT(*) FROM transactions WHERE account_id IS NULL AND deleted_at IS NULL;",
+        [],
+        |row| row.get(0),
+    )?;
+    if unassigned > 0 {
+        accounts.push((None, "unassigned".to_string(), 0.0));
+    }
+
+    // everything before the range is summed up under the start date
+    let before_range = query_account_sums(
+        db,
+        "
+        SELECT t.account_id, (:start_date), SUM(t.value) FROM transactions t
+        WHERE t.date_created < (:start_date) AND t.deleted_at IS NULL
+        GROUP BY t.account_id
+        ",
+        named_params! {
+            ":start_date": start_date,
+        },
+    )?;
+
+    let daily = query_account_sums(
+        db,
+        "
+        SELECT t.account_id, date(t.date_created) as day, SUM(t.value) FROM transactions t
+        WHERE t.date_created >= (:start_date) AND t.date_created < (:end_date)
+        AND t.deleted_at IS NULL
+        GROUP BY t.account_id, day
+        ",
+        named_params! {
+            ":start_date": start_date,
+            ":end_date": end_of_day(end_date)?,
+        },
+    )?;
+
+    let mut history = Vec::new();
+    for (account_id, label, opening_balance) in accounts {
+        let mut balance = opening_balance
+            + before_range
+                .get(&(account_id, start_date.clone()))
+                .unwrap_or(&0.0);
+
+        let mut dates: HashMap<String, f64> = HashMap::new();
+        for day in start.iter_days().take_while(|day| *day <= end) {
+            let day_key = format_date(day);
+            balance += daily.get(&(account_id, day_key.clone())).unwrap_or(&0.0);
+
+            let key = match interval {
+                BalanceInterval::DAY => day_key,
+                // overwritten each day so that the month ends up with the last balance in range
+                BalanceInterval::MONTH => day.format("%Y-%m").to_string(),
+            };
+            dates.insert(key, balance);
+        }
+
+        history.push(AccountBalanceHistory {
+            account_id,
+            label,
+            dates,
+        });
+    }
+
+    Ok(history)
+}
+
+fn last_day_of_month(date: NaiveDate) -> NaiveDate {
+    let first_of_next = match date.month() {
+        12 => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd_opt(date.year(), month + 1, 1),
+    };
+    first_of_next.and_then(|d| d.pred_opt()).unwrap_or(date)
+}
+
+fn net_worth_at(db: &Connection, date: NaiveDate) -> Result<(f64, f64, f64), rusqlite::Error> {
+    let date = format_date(date);
+    let accounts = crate::account_service::get_total_balance(db, &date)?;
+    let (assets, liabilities) = crate::asset_service::get_valuation_totals(db, &date)?;
+    Ok((accounts, assets, liabilities))
+}
+
+// Net worth at the end of each month between the given dates, changes are compared against
+// the end of the previous month
+pub fn get_net_worth_report(
+    db: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<NetWorthMonth>, rusqlite::Error> {
+    let start = parse_date(start_date)?;
+    let end = parse_date(end_date)?;
+    if start > end {
+        return Err(invalid_input("start date must not be after end date"));
+    }
+
+    let month_before_start = start.with_day(1).and_then(|d| d.pred_opt()).unwrap_or(start);
+    let (mut previous_accounts, mut previous_assets, mut previous_liabilities) =
+        net_worth_at(db, month_before_start)?;
+
+    let mut months = Vec::new();
+    let mut month_end = last_day_of_month(start);
+    loop {
+        let (accounts, assets, liabilities) = net_worth_at(db, month_end)?;
+        let net_worth = accounts + assets - liabilities;
+        let previous_net_worth = previous_accounts + previous_assets - previous_liabilities;
+
+        months.push(NetWorthMonth {
+            month: month_end.format("%Y-%m").to_string(),
+            accounts,
+            assets,
+            liabilities,
+            net_worth,
+            accounts_change: accounts - previous_accounts,
+            assets_change: assets - previous_assets,
+            liabilities_change: liabilities - previous_liabilities,
+            net_worth_change: net_worth - previous_net_worth,
+        });
+
+        if month_end >= end {
+            break;
+        }
+        (previous_accounts, previous_assets, previous_liabilities) = (accounts, assets, liabilities);
+        month_end = last_day_of_month(month_end + chrono::Duration::days(1));
+    }
+
+    Ok(months)
+}
+
+// Average monthly spending per category over the full months preceding the given date.
+// Transactions with the name of a recurring transaction of the forecast are left out, as
+// those are already projected on their own dates.
+fn get_average_category_spending(
+    db: &Connection,
+    before: NaiveDate,
+    months: u32,
+    account_id: Option<i32>,
+) -> Result<HashMap<String, f64>, rusqlite::Error> {
+    if months == 0 {
+        return Ok(HashMap::new());
+    }
+    let end = before.with_day(1).unwrap_or(before);
+    let start = end
+        .checked_sub_months(Months::new(months))
+        .ok_or(invalid_date(&format_date(before)))?;
+
+    let groups = get_category_groups_where(
+        db,
+        (start, end),
+        DateField::DATE_CREATED,
+        "
+        AND (:account_id IS NULL OR t.account_id = (:account_id))
+        AND NOT EXISTS (
+            SELECT 1 FROM recurring_transactions r
+            WHERE r.name = t.name COLLATE NOCASE
+            AND (r.account_id = t.account_id OR (r.account_id IS NULL AND :account_id IS NULL))
+        )
+        ",
+        &[(":account_id", &account_id)],
+    )?;
+
+    Ok(groups
+        .expenses
+        .into_iter()
+        .map(|(labels, sum)| (labels, sum / months as f64))
+        .collect())
+}
+
+// Projects the balance at the end of each day after the start date using the recurring
+// transactions. When history_months is above zero the average category spending of the
+// preceding months is spread evenly over every projected day, this average is based on
+// the transactions of the forecast account only.
+pub fn get_forecast(
+    db: &Connection,
+    start_date: &str,
+    days: i64,
+    account_id: Option<i32>,
+    history_months: u32,
+) -> Result<Forecast, rusqlite::Error> {
+    let start = parse_date(start_date)?;
+    if days < 1 {
+        return Err(invalid_input("days must be at least 1"));
+    }
+    let end = start + chrono::Duration::days(days);
+    let start_date = format_date(start);
+
+    let starting_balance = match account_id {
+        Some(id) => crate::account_service::get_account_balance(db, id, &start_date)?,
+        None => crate::account_service::get_total_balance(db, &start_date)?,
+    };
+
+    let mut changes: HashMap<NaiveDate, f64> = HashMap::new();
+    for recurring in crate::recurring_service::get_recurring_transactions(db)? {
+        if account_id.is_some() && recurring.account_id != account_id {
+            continue;
+        }
+        let from = start + chrono::Duration::days(1);
+        for date in crate::recurring_service::get_occurrences(&recurring, from, end)? {
+            *changes.entry(date).or_default() += recurring.value;
+        }
+    }
+
+    let average_category_spending =
+        get_average_category_spending(db, start, history_months, account_id)?;
+    let daily_spending = match history_months {
+        0 => 0.0,
+        // a month is averaged to 30.44 days
+        _ => average_category_spending.values().sum::<f64>() * 12.0 / 365.25,
+    };
+
+    let mut balance = starting_balance;
+    let mut points: Vec<ForecastPoint> = Vec::new();
+    for day in start.iter_days().skip(1).take(days as usize) {
+        balance += changes.get(&day).unwrap_or(&0.0) + daily_spending;
+        points.push(ForecastPoint {
+            date: format_date(day),
+            balance,
+        });
+    }
+
+    // a low point is the last day before the balance rises again, e.g. the day before payday
+    let mut low_points = Vec::new();
+    let mut last_different = starting_balance;
+    for (index, point) in points.iter().enumerate() {
+        if index > 0 && points[index - 1].balance != point.balance {
+            last_different = points[index - 1].balance;
+        }
+        if let Some(next) = points.get(index + 1) {
+            if point.balance < last_different && point.balance < next.balance {
+                low_points.push(ForecastPoint {
+                    date: point.date.clone(),
+                    balance: point.balance,
+                });
+            }
+        }
+    }
+
+    let lowest = points
+        .iter()
+        .min_by(|a, b| a.balance.total_cmp(&b.balance))
+        .map(|point| ForecastPoint {
+            date: point.date.clone(),
+            balance: point.balance,
+        })
+        .unwrap_or(ForecastPoint {
+            date: start_date,
+            balance: starting_balance,
+        });
+
+    let first_negative_date = points
+        .iter()
+        .find(|point| point.balance < 0.0)
+        .map(|point| point.date.clone());
+
+    Ok(Forecast {
+        starting_balance,
+        dates: points
+            .into_iter()
+            .map(|point| (point.date, point.balance))
+            .collect(),
+        average_category_spending,
+        lowest,
+        low_points,
+        first_negative_date,
+    })
+}
+
 #[cfg(test)]
 use crate::migration_service::init_db_in_memory;
 
 #[test]
-fn date_selector_should_return_year() -> Result<(), rusqlite::Error> {
-    let date = date_selector("2023-11-01", ReportType::YEAR)?;
-    assert_eq!(date, "2023-__-__");
+fn report_range_should_return_year() -> Result<(), rusqlite::Error> {
+    let range = report_range("2023-11-15", ReportType::YEAR)?;
+    assert_eq!(range, (parse_date("2023-01-01")?, parse_date("2024-01-01")?));
     Ok(())
 }
 
 #[test]
-fn date_selector_should_return_month() -> Result<(), rusqlite::Error> {
-    let date = date_selector("2023-11-01", ReportType::MONTH)?;
-    assert_eq!(date, "2023-11-__");
+fn report_range_should_return_month() -> Result<(), rusqlite::Error> {
+    let range = report_range("2023-12-15", ReportType::MONTH)?;
+    assert_eq!(range, (parse_date("2023-12-01")?, parse_date("2024-01-01")?));
     Ok(())
 }
 
 #[test]
-fn date_selector_should_return_error() -> Result<(), rusqlite::Error> {
-    let date = date_selector("01-01", ReportType::MONTH);
-    assert!(date.is_err());
+fn report_range_should_return_error() -> Result<(), rusqlite::Error> {
+    assert!(report_range("01-01", ReportType::MONTH).is_err());
+    assert!(report_range("2023-02-30", ReportType::MONTH).is_err());
     Ok(())
 }
 
 #[test]
 fn should_return_correct_total_and_uncategorized() -> Result<(), rusqlite::Error> {
     let conn = init_db_in_memory()?;
-    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
 
     let report = get_basic_report(&conn, ReportType::MONTH, "2023-11-01")?;
 
@@ -265,6 +905,7 @@ fn should_return_zero_for_uncategorized() -> Result<(), rusqlite::Error> {
         "test",
         None,
         "2023-11-01",
+        None,
         vec![category.id],
     )?;
 
@@ -288,6 +929,7 @@ fn should_return_correct_total_when_mix_of_income_and_expenses() -> Result<(), r
         "test",
         None,
         "2023-11-01",
+        None,
         vec![category.id],
     )?;
 
@@ -297,6 +939,7 @@ fn should_return_correct_total_when_mix_of_income_and_expenses() -> Result<(), r
         "test",
         None,
         "2023-11-01",
+        None,
         vec![category.id],
     )?;
 
@@ -355,8 +998,8 @@ fn report_should_fill_dates_with_empty_values_for_month_content() -> Result<(),
 fn year_report_should_combine_sums() -> Result<(), rusqlite::Error> {
 
     let conn = init_db_in_memory()?;
-    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![])?;
-    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-02", vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-02", None, vec![])?;
 
     let report = get_basic_report(&conn, ReportType::YEAR, "2023-11-01")?;
     
@@ -369,8 +1012,8 @@ fn year_report_should_combine_sums() -> Result<(), rusqlite::Error> {
 #[test]
 fn month_report_should_combine_sums_for_days() -> Result<(), rusqlite::Error> {
     let conn = init_db_in_memory()?;
-    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![])?;
-    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
 
     let report = get_basic_report(&conn, ReportType::MONTH, "2023-11-01")?;
 
@@ -380,7 +1023,494 @@ fn month_report_should_combine_sums_for_days() -> Result<(), rusqlite::Error> {
     Ok(())
 }
 
+#[test]
+fn balance_history_should_start_from_opening_balance() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::account_service::insert_account(&conn, "checking", 100.0)?;
+    let account = &crate::account_service::get_accounts(&conn)?[0];
+
+    crate::transaction_service::insert_transaction(&conn, -20.0, "test", None, "2023-10-15", Some(account.id), vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 5.0, "test", None, "2023-11-02", Some(account.id), vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-02", None, vec![])?;
+
+    let history = get_balance_history(&conn, BalanceInterval::DAY, "2023-11-01", "2023-11-03")?;
+
+    assert_eq!(history.len(), 2);
+    let checking = &history[0];
+    assert_eq!(checking.account_id, Some(account.id));
+    assert_eq!(checking.dates.len(), 3);
+    assert_eq!(checking.dates.get("2023-11-01").unwrap(), &80.0);
+    assert_eq!(checking.dates.get("2023-11-02").unwrap(), &85.0);
+    assert_eq!(checking.dates.get("2023-11-03").unwrap(), &85.0);
+
+    let unassigned = &history[1];
+    assert_eq!(unassigned.account_id, None);
+    assert_eq!(unassigned.dates.get("2023-11-01").unwrap(), &0.0);
+    assert_eq!(unassigned.dates.get("2023-11-03").unwrap(), &1.0);
+
+    Ok(())
+}
+
+#[test]
+fn balance_history_should_group_by_month() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::account_service::insert_account(&conn, "checking", 10.0)?;
+    let account = &crate::account_service::get_accounts(&conn)?[0];
+
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-10-15", Some(account.id), vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 2.0, "test", None, "2023-11-30", Some(account.id), vec![])?;
+
+    let history = get_balance_history(&conn, BalanceInterval::MONTH, "2023-10-01", "2023-12-31")?;
+
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].dates.len(), 3);
+    assert_eq!(history[0].dates.get("2023-10").unwrap(), &11.0);
+    assert_eq!(history[0].dates.get("2023-11").unwrap(), &13.0);
+    assert_eq!(history[0].dates.get("2023-12").unwrap(), &13.0);
+
+    Ok(())
+}
+
+#[test]
+fn balance_history_should_reject_invalid_range() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    assert!(get_balance_history(&conn, BalanceInterval::DAY, "2023-11", "2023-12-01").is_err());
+    let history = get_balance_history(&conn, BalanceInterval::DAY, "2023-12-01", "2023-11-01");
+    assert!(history.is_err_and(|e| e.to_string().contains("start date must not be after end date")));
+    Ok(())
+}
+
+#[test]
+fn net_worth_should_combine_accounts_and_valuations() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::account_service::insert_account(&conn, "checking", 1000.0)?;
+    let account = &crate::account_service::get_accounts(&conn)?[0];
+    crate::asset_service::insert_asset(&conn, "house", crate::models::AssetKind::ASSET)?;
+    crate::asset_service::insert_asset(&conn, "mortgage", crate::models::AssetKind::LIABILITY)?;
+    let assets = crate::asset_service::get_assets(&conn)?;
+
+    crate::asset_service::set_asset_valuation(&conn, assets[0].id, "2023-09-01", 200000.0)?;
+    crate::asset_service::set_asset_valuation(&conn, assets[1].id, "2023-09-01", 150000.0)?;
+    crate::asset_service::set_asset_valuation(&conn, assets[1].id, "2023-11-01", 149000.0)?;
+    crate::transaction_service::insert_transaction(&conn, -1000.0, "mortgage", None, "2023-11-01", Some(account.id), vec![])?;
+
+    let report = get_net_worth_report(&conn, "2023-10-15", "2023-11-15")?;
+
+    assert_eq!(report.len(), 2);
+    assert_eq!(report[0].month, "2023-10");
+    assert_eq!(report[0].net_worth, 51000.0);
+    assert_eq!(report[0].net_worth_change, 0.0);
+
+    assert_eq!(report[1].month, "2023-11");
+    assert_eq!(report[1].accounts, 0.0);
+    assert_eq!(report[1].assets, 200000.0);
+    assert_eq!(report[1].liabilities, 149000.0);
+    assert_eq!(report[1].net_worth, 51000.0);
+    assert_eq!(report[1].accounts_change, -1000.0);
+    assert_eq!(report[1].liabilities_change, -1000.0);
+    assert_eq!(report[1].net_worth_change, 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn net_worth_should_return_every_month_in_range() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    let report = get_net_worth_report(&conn, "2023-11-01", "2024-02-01")?;
+    let months: Vec<&str> = report.iter().map(|m| m.month.as_str()).collect();
+    assert_eq!(months, vec!["2023-11", "2023-12", "2024-01", "2024-02"]);
+
+    let report = get_net_worth_report(&conn, "2024-02-01", "2023-11-01");
+    assert!(report.is_err_and(|e| e.to_string().contains("start date must not be after end date")));
+    Ok(())
+}
+
+#[test]
+fn forecast_should_find_low_point_before_income() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::account_service::insert_account(&conn, "checking", 100.0)?;
+    let account = &crate::account_service::get_accounts(&conn)?[0];
+    crate::recurring_service::insert_recurring_transaction(
+        &conn,
+        -150.0,
+        "rent",
+        Some(account.id),
+        crate::models::Frequency::MONTHLY,
+        "2023-11-03",
+        None,
+    )?;
+    crate::recurring_service::insert_recurring_transaction(
+        &conn,
+        1000.0,
+        "salary",
+        Some(account.id),
+        crate::models::Frequency::MONTHLY,
+        "2023-11-05",
+        None,
+    )?;
+
+    let forecast = get_forecast(&conn, "2023-11-01", 10, Some(account.id), 0)?;
+
+    assert_eq!(forecast.starting_balance, 100.0);
+    assert_eq!(forecast.dates.len(), 10);
+    assert_eq!(forecast.dates.get("2023-11-02").unwrap(), &100.0);
+    assert_eq!(forecast.dates.get("2023-11-11").unwrap(), &950.0);
+    assert_eq!(
+        forecast.lowest,
+        ForecastPoint {
+            date: "2023-11-03".to_string(),
+            balance: -50.0
+        }
+    );
+    assert_eq!(forecast.low_points.len(), 1);
+    assert_eq!(forecast.low_points[0].date, "2023-11-04");
+    assert_eq!(forecast.first_negative_date, Some("2023-11-03".to_string()));
+
+    let error = get_forecast(&conn, "2023-11-01", 0, Some(account.id), 0).unwrap_err();
+    assert!(error.to_string().contains("days must be at least 1"));
+
+    Ok(())
+}
+
+#[test]
+fn forecast_should_include_average_category_spending() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "groceries")?;
+    let category = &crate::category_service::get_categories(&conn)?[0];
+    crate::transaction_service::insert_transaction(&conn, -60.0, "food", None, "2023-09-10", None, vec![category.id])?;
+    crate::transaction_service::insert_transaction(&conn, -30.0, "food", None, "2023-10-10", None, vec![category.id])?;
+
+    let forecast = get_forecast(&conn, "2023-11-01", 5, None, 2)?;
+
+    assert_eq!(forecast.starting_balance, -90.0);
+    assert_eq!(forecast.average_category_spending.get("groceries").unwrap(), &-45.0);
+    assert!(forecast.lowest.balance < -90.0);
+    assert_eq!(forecast.lowest.date, "2023-11-06");
+
+    Ok(())
+}
+
+#[test]
+fn forecast_should_not_average_recurring_transactions() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::account_service::insert_account(&conn, "checking", 1000.0)?;
+    crate::account_service::insert_account(&conn, "savings", 0.0)?;
+    let accounts = crate::account_service::get_accounts(&conn)?;
+    let (checking, savings) = (accounts[0].id, accounts[1].id);
+    crate::category_service::insert_category(&conn, "housing")?;
+    crate::category_service::insert_category(&conn, "groceries")?;
+    let categories = crate::category_service::get_categories(&conn)?;
+    let housing = categories.iter().find(|c| c.label == "housing").unwrap().id;
+    let groceries = categories.iter().find(|c| c.label == "groceries").unwrap().id;
+    crate::recurring_service::insert_recurring_transaction(
+        &conn,
+        -500.0,
+        "Rent",
+        Some(checking),
+        crate::models::Frequency::MONTHLY,
+        "2023-09-03",
+        None,
+    )?;
+    for date in ["2023-09-03", "2023-10-03"] {
+        crate::transaction_service::insert_transaction(&conn, -500.0, "rent", None, date, Some(checking), vec![housing])?;
+    }
+    crate::transaction_service::insert_transaction(&conn, -60.0, "food", None, "2023-09-10", Some(checking), vec![groceries])?;
+    crate::transaction_service::insert_transaction(&conn, -30.0, "food", None, "2023-10-10", Some(checking), vec![groceries])?;
+    crate::transaction_service::insert_transaction(&conn, -200.0, "food", None, "2023-10-12", Some(savings), vec![groceries])?;
+
+    let forecast = get_forecast(&conn, "2023-11-01", 5, Some(checking), 2)?;
+
+    // the rent is only projected on its date, the other account is left out
+    assert_eq!(forecast.average_category_spending.get("housing"), None);
+    assert_eq!(forecast.average_category_spending.get("groceries").unwrap(), &-45.0);
+    let daily = -45.0 * 12.0 / 365.25;
+    assert_eq!(forecast.starting_balance, -90.0);
+    assert!((forecast.dates.get("2023-11-02").unwrap() - (-90.0 + daily)).abs() < 1e-9);
+    assert!((forecast.dates.get("2023-11-03").unwrap() - (-590.0 + daily * 2.0)).abs() < 1e-9);
+
+    Ok(())
+}
+
+#[test]
+fn comparison_should_return_deltas_against_previous_month() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    crate::category_service::insert_category(&conn, "travel")?;
+    crate::category_service::insert_category(&conn, "gifts")?;
+    let categories = crate::category_service::get_categories(&conn)?;
+
+    crate::transaction_service::insert_transaction(&conn, -100.0, "test", None, "2023-10-05", None, vec![categories[0].id])?;
+    crate::transaction_service::insert_transaction(&conn, -50.0, "test", None, "2023-10-05", None, vec![categories[2].id])?;
+    crate::transaction_service::insert_transaction(&conn, -150.0, "test", None, "2023-11-05", None, vec![categories[0].id])?;
+    crate::transaction_service::insert_transaction(&conn, -20.0, "test", None, "2023-11-06", None, vec![categories[1].id])?;
+
+    let report = get_comparison_report(&conn, ReportType::MONTH, "2023-11-15", ComparisonPeriod::PREVIOUS_PERIOD, None, DateField::DATE_CREATED)?;
+
+    assert_eq!(report.previous_date, "2023-10-15");
+    assert_eq!(report.total.current, -170.0);
+    assert_eq!(report.total.previous, -150.0);
+    assert_eq!(report.total.delta, -20.0);
+
+    let food = report.categories.iter().find(|c| c.label == "food").unwrap();
+    assert_eq!(food.delta, -50.0);
+    assert_eq!(food.percentage, Some(-50.0));
+
+    let travel = report.categories.iter().find(|c| c.label == "travel").unwrap();
+    assert_eq!(travel.previous, 0.0);
+    assert_eq!(travel.percentage, None);
+
+    assert_eq!(report.appeared, vec!["travel".to_string()]);
+    assert_eq!(report.vanished, vec!["gifts".to_string()]);
+
+    Ok(())
+}
+
+#[test]
+fn comparison_should_select_compared_period() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+
+    let report = get_comparison_report(&conn, ReportType::MONTH, "2023-11-15", ComparisonPeriod::SAME_PERIOD_LAST_YEAR, None, DateField::DATE_CREATED)?;
+    assert_eq!(report.previous_date, "2022-11-15");
+
+    let report = get_comparison_report(&conn, ReportType::YEAR, "2023-11-15", ComparisonPeriod::PREVIOUS_PERIOD, None, DateField::DATE_CREATED)?;
+    assert_eq!(report.previous_date, "2022-11-15");
+
+    let report = get_comparison_report(&conn, ReportType::MONTH, "2023-11-15", ComparisonPeriod::CUSTOM, Some("2021-02-01"), DateField::DATE_CREATED)?;
+    assert_eq!(report.previous_date, "2021-02-01");
+
+    let report = get_comparison_report(&conn, ReportType::MONTH, "2023-11-15", ComparisonPeriod::CUSTOM, None, DateField::DATE_CREATED);
+    assert!(report.is_err_and(|e| e.to_string().contains("a custom comparison needs a compare date")));
+
+    Ok(())
+}
+
+#[test]
+fn trend_should_return_matrix_of_months() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    crate::category_service::insert_category(&conn, "travel")?;
+    let categories = crate::category_service::get_categories(&conn)?;
+
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test", None, "2023-09-05", None, vec![categories[0].id])?;
+    crate::transaction_service::insert_transaction(&conn, -20.0, "test", None, "2023-10-05", None, vec![categories[0].id, categories[1].id])?;
+    crate::transaction_service::insert_transaction(&conn, -30.0, "test", None, "2023-11-05", None, vec![categories[0].id])?;
+    // outside of the range
+    crate::transaction_service::insert_transaction(&conn, -40.0, "test", None, "2023-08-31", None, vec![categories[0].id])?;
+
+    let report = get_trend_report(&conn, "2023-11-15", 3, DateField::DATE_CREATED)?;
+
+    assert_eq!(report.months, vec!["2023-09", "2023-10", "2023-11"]);
+    assert_eq!(report.categories.len(), 2);
+
+    let food = &report.categories[0];
+    assert_eq!(food.label, "food");
+    assert_eq!(food.months.get("2023-09").unwrap(), &-10.0);
+    assert_eq!(food.average, -20.0);
+    assert_eq!(food.min, -30.0);
+    assert_eq!(food.max, -10.0);
+    assert_eq!(food.slope, -10.0);
+
+    let travel = &report.categories[1];
+    assert_eq!(travel.months.len(), 3);
+    assert_eq!(travel.months.get("2023-09").unwrap(), &0.0);
+    assert_eq!(travel.max, 0.0);
+    assert_eq!(travel.slope, 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn trend_should_span_years() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    let report = get_trend_report(&conn, "2024-01-15", 24, DateField::DATE_CREATED)?;
+
+    assert_eq!(report.months.len(), 24);
+    assert_eq!(report.months[0], "2022-02");
+    assert_eq!(report.months[23], "2024-01");
+    let report = get_trend_report(&conn, "2024-01-15", 0, DateField::DATE_CREATED);
+    assert!(report.is_err_and(|e| e.to_string().contains("months must be at least 1")));
+
+    Ok(())
+}
+
+#[test]
+fn top_payees_should_be_ordered_by_total() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::payee_service::insert_payee(&conn, "Amazon")?;
+    crate::payee_service::insert_payee(&conn, "Grocery")?;
+    let payees = crate::payee_service::get_payees(&conn)?;
+    crate::payee_service::add_payee_pattern(&conn, payees[0].id, "amazon*")?;
+
+    crate::transaction_service::insert_transaction(&conn, -10.0, "AMAZON 1", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -15.0, "amazon.de", None, "2023-11-02", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -5.0, "grocery", None, "2023-11-02", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -50.0, "grocery", None, "2023-10-02", None, vec![])?;
+
+    let top = get_top_payees(&conn, ReportType::MONTH, "2023-11-01", 10, DateField::DATE_CREATED)?;
+
+    assert_eq!(top.len(), 2);
+    assert_eq!(top[0].label, "Amazon");
+    assert_eq!(top[0].total, -25.0);
+    assert_eq!(top[0].count, 2);
+    assert_eq!(top[1].label, "Grocery");
+    assert_eq!(top[1].total, -5.0);
+
+    let top = get_top_payees(&conn, ReportType::YEAR, "2023-11-01", 1, DateField::DATE_CREATED)?;
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].label, "Grocery");
+
+    Ok(())
+}
+
+#[test]
+fn tag_report_should_sum_by_tag_in_range() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::tag_service::insert_tag(&conn, "vacation")?;
+    crate::tag_service::insert_tag(&conn, "reimbursable")?;
+    let tags = crate::tag_service::get_tags(&conn)?;
+
+    crate::transaction_service::insert_transaction(&conn, -100.0, "hotel", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -20.0, "taxi", None, "2023-11-02", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -50.0, "old", None, "2023-01-02", None, vec![])?;
+    crate::tag_service::add_transaction_tag(&conn, 1, tags[0].id)?;
+    crate::tag_service::add_transaction_tag(&conn, 2, tags[0].id)?;
+    crate::tag_service::add_transaction_tag(&conn, 2, tags[1].id)?;
+    crate::tag_service::add_transaction_tag(&conn, 3, tags[1].id)?;
+
+    let report = get_tag_report(&conn, "2023-10-01", "2023-11-30", DateField::DATE_CREATED)?;
+
+    assert_eq!(report.len(), 2);
+    assert_eq!(report[0].label, "reimbursable");
+    assert_eq!(report[0].total, -20.0);
+    assert_eq!(report[0].count, 1);
+    assert_eq!(report[1].label, "vacation");
+    assert_eq!(report[1].total, -120.0);
+    assert_eq!(report[1].count, 2);
+
+    // tags should not show up in the category based reports
+    let basic = get_basic_report(&conn, ReportType::MONTH, "2023-11-01")?;
+    assert!(basic.category_expenses.is_empty());
+    assert_eq!(basic.uncategorized, -120.0);
+
+    Ok(())
+}
+
 // TODO should add a test where categories are tested, so that grouping is working correctly
 // for example if we have category 'foo', 'bar'
 // we can have a transaction that is listed under
 // 'foo, bar', 'foo' or 'bar'
+
+#[test]
+fn report_should_include_transactions_with_time_on_last_day() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::transaction_service::insert_transaction(&conn, -5.0, "test", None, "2023-11-30 23:15", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -7.0, "test", None, "2023-12-01 00:00", None, vec![])?;
+
+    let report = get_basic_report(&conn, ReportType::MONTH, "2023-11-01")?;
+    assert_eq!(report.total, -5.0);
+    assert_eq!(report.dates.get("2023-11-30"), Some(&-5.0));
+    Ok(())
+}
+
+#[test]
+fn report_should_bucket_by_chosen_date() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::transaction_service::insert_transaction(&conn, -5.0, "test", None, "2023-11-30", None, vec![])?;
+    crate::transaction_service::set_transaction_bank_dates(&conn, 1, Some("2023-12-02"), None)?;
+    crate::transaction_service::insert_transaction(&conn, -7.0, "test", None, "2023-12-01", None, vec![])?;
+
+    let report = get_basic_report_by(&conn, ReportType::MONTH, "2023-12-01", DateField::BOOKING_DATE)?;
+    assert_eq!(report.total, -12.0);
+    assert_eq!(report.dates.get("2023-12-02"), Some(&-5.0));
+
+    // value date is not set so the transaction stays in november
+    let report = get_basic_report_by(&conn, ReportType::MONTH, "2023-12-01", DateField::VALUE_DATE)?;
+    assert_eq!(report.total, -7.0);
+
+    // the other reports place transactions by the chosen date as well
+    crate::tag_service::insert_tag(&conn, "vacation")?;
+    crate::tag_service::add_transaction_tag(&conn, 1, 1)?;
+    let tags = get_tag_report(&conn, "2023-12-01", "2023-12-31", DateField::BOOKING_DATE)?;
+    assert_eq!(tags.len(), 1);
+    assert!(get_tag_report(&conn, "2023-12-01", "2023-12-31", DateField::DATE_CREATED)?.is_empty());
+
+    crate::category_service::insert_category(&conn, "food")?;
+    crate::bulk_service::bulk_update(
+        &conn,
+        &crate::models::TransactionSelection::IDS(vec![1, 2]),
+        &crate::models::BulkOperation::ADD_CATEGORY(1),
+    )?;
+    let trend = get_trend_report(&conn, "2023-12-01", 2, DateField::BOOKING_DATE)?;
+    assert_eq!(trend.categories[0].months.get("2023-11"), Some(&0.0));
+    assert_eq!(trend.categories[0].months.get("2023-12"), Some(&-12.0));
+
+    let comparison = get_comparison_report(&conn, ReportType::MONTH, "2023-12-01", ComparisonPeriod::PREVIOUS_PERIOD, None, DateField::BOOKING_DATE)?;
+    assert_eq!(comparison.total.current, -12.0);
+    assert_eq!(comparison.total.previous, 0.0);
+    Ok(())
+}
+
+#[test]
+fn report_should_include_colors_of_archived_categories() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    crate::category_service::update_category_metadata(
+        &conn,
+        1,
+        Some("#112233"),
+        None,
+        crate::models::CategoryKind::EXPENSE,
+        true,
+    )?;
+    crate::transaction_service::insert_transaction(&conn, -5.0, "test", None, "2023-11-01", None, vec![1])?;
+
+    let report = get_basic_report(&conn, ReportType::MONTH, "2023-11-01")?;
+    assert_eq!(report.category_expenses.get("food"), Some(&-5.0));
+    assert_eq!(report.category_colors.get("food").map(|c| c.as_str()), Some("#112233"));
+    Ok(())
+}
+
+#[test]
+fn report_should_name_categories_by_display_label() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "Groceries")?;
+    crate::category_service::insert_category(&conn, "eBay")?;
+    crate::transaction_service::insert_transaction(&conn, -5.0, "test", None, "2023-11-01", None, vec![1, 2])?;
+
+    let report = get_basic_report(&conn, ReportType::MONTH, "2023-11-01")?;
+    assert_eq!(report.category_expenses.get("Groceries, eBay"), Some(&-5.0));
+    let trend = get_trend_report(&conn, "2023-11-01", 1, DateField::DATE_CREATED)?;
+    assert_eq!(trend.categories[0].label, "eBay");
+    Ok(())
+}
+
+#[test]
+fn report_should_split_income_and_expenses_by_category_kind() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "groceries")?;
+    crate::category_service::insert_category(&conn, "salary")?;
+    crate::category_service::insert_category(&conn, "gifts")?;
+    crate::category_service::update_category_metadata(&conn, 1, Some("#111111"), None, crate::models::CategoryKind::EXPENSE, false)?;
+    crate::category_service::update_category_metadata(&conn, 2, Some("#222222"), None, crate::models::CategoryKind::INCOME, false)?;
+    crate::category_service::update_category_metadata(&conn, 3, Some("#333333"), None, crate::models::CategoryKind::BOTH, false)?;
+    crate::transaction_service::insert_transaction(&conn, -50.0, "market", None, "2023-11-01", None, vec![1])?;
+    crate::transaction_service::insert_transaction(&conn, 10.0, "refund", None, "2023-11-02", None, vec![1])?;
+    crate::transaction_service::insert_transaction(&conn, 2000.0, "employer", None, "2023-11-03", None, vec![2])?;
+    crate::transaction_service::insert_transaction(&conn, 30.0, "present", None, "2023-11-04", None, vec![3])?;
+    crate::transaction_service::insert_transaction(&conn, -20.0, "present", None, "2023-11-05", None, vec![3, 1])?;
+
+    let report = get_basic_report(&conn, ReportType::MONTH, "2023-11-01")?;
+    // the refund lowers the expenses instead of showing up as income
+    assert_eq!(report.category_expenses.get("groceries"), Some(&-40.0));
+    assert_eq!(report.category_income.get("groceries"), None);
+    assert_eq!(report.category_income.get("salary"), Some(&2000.0));
+    assert_eq!(report.category_income.get("gifts"), Some(&30.0));
+
+    // groups are labeled and colored in the order of the categories
+    assert_eq!(report.category_expenses.get("groceries, gifts"), Some(&-20.0));
+    assert_eq!(report.category_colors.get("groceries, gifts").map(|c| c.as_str()), Some("#111111"));
+    crate::category_service::set_category_order(&conn, vec![3, 2, 1])?;
+    let report = get_basic_report(&conn, ReportType::MONTH, "2023-11-01")?;
+    assert_eq!(report.category_colors.get("gifts, groceries").map(|c| c.as_str()), Some("#333333"));
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use crate::models::{FirstDayOfWeek, ReportType, Settings};
+use chrono::format::{Item, StrftimeItems};
+use rusqlite::{named_params, Connection};
+use std::error::Error;
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            page_size: 15,
+            base_currency: "EUR".to_string(),
+            first_day_of_week: FirstDayOfWeek::MONDAY,
+            date_format: "%Y-%m-%d".to_string(),
+            default_report_type: ReportType::MONTH,
+            trash_retention_days: crate::trash_service::DEFAULT_RETENTION_DAYS,
+        }
+    }
+}
+
+fn validate(settings: &Settings) -> Result<(), String> {
+    if !(1..=1000).contains(&settings.page_size) {
+        return Err("page_size must be between 1 and 1000".to_string());
+    }
+    // ISO 4217 currency code
+    if settings.base_currency.len() != 3
+        || !settings.base_currency.chars().all(|c| c.is_ascii_uppercase())
+    {
+        return Err("base_currency must be a three letter currency code, e.g. EUR".to_string());
+    }
+    if settings.date_format.trim().is_empty()
+        || StrftimeItems::new(&settings.date_format).any(|item| item == Item::Error)
+    {
+        return Err(format!("Invalid date_format: {:?}", settings.date_format));
+    }
+    if settings.trash_retention_days > 3650 {
+        return Err("trash_retention_days must be at most 3650".to_string());
+    }
+    Ok(())
+}
+
+// Applies the value of a single setting, the value is checked against the type of the setting
+fn with_value(
+    settings: &Settings,
+    key: &str,
+    value: &serde_json::Value,
+) -> Result<Settings, String> {
+    let mut fields = serde_json::to_value(settings).map_err(|e| e.to_string())?;
+    match fields.get_mut(key) {
+        Some(field) => *field = value.clone(),
+        None => return Err(format!("Unknown setting: {:?}", key)),
+    }
+
+    let settings: Settings = serde_json::from_value(fields)
+        .map_err(|e| format!("Invalid value for {}: {}", key, e))?;
+    validate(&settings)?;
+    Ok(settings)
+}
+
+// Settings which are not stored, or whose stored value is no longer valid, use their default
+pub fn get_settings(db: &Connection) -> Result<Settings, rusqlite::Error> {
+    let mut stmt = db.prepare("SELECT key, value FROM settings;")?;
+    let mut rows = stmt.query([])?;
+
+    let mut settings = Settings::default();
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        let value: serde_json::Value = row.get(1)?;
+        if let Ok(updated) = with_value(&settings, &key, &value) {
+            settings = updated;
+        }
+    }
+    Ok(settings)
+}
+
+// Returns the settings including the changed value
+pub fn set_setting(
+    db: &Connection,
+    key: &str,
+    value: &serde_json::Value,
+) -> Result<Settings, Box<dyn Error>> {
+    let settings = with_value(&get_settings(db)?, key, value)?;
+    db.execute(
+        "
+        INSERT INTO settings (key, value) VALUES (:key, :value)
+        ON CONFLICT (key) DO UPDATE SET value = excluded.value;
+        ",
+        named_params! {
+            ":key": key,
+            ":value": value,
+        },
+    )?;
+    Ok(settings)
+}
+
+// Removes the stored value so that the default is used again
+pub fn reset_setting(db: &Connection, key: &str) -> Result<Settings, rusqlite::Error> {
+    db.execute(
+        "DELETE FROM settings WHERE key = (:key);",
+        named_params! {
+            ":key": key,
+        },
+    )?;
+    get_settings(db)
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn settings_should_default_when_empty() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    assert_eq!(get_settings(&conn)?, Settings::default());
+    Ok(())
+}
+
+#[test]
+fn set_setting_should_be_persisted() -> Result<(), Box<dyn Error>> {
+    let conn = init_db_in_memory()?;
+    set_setting(&conn, "page_size", &serde_json::json!(50))?;
+    set_setting(&conn, "first_day_of_week", &serde_json::json!("SUNDAY"))?;
+    set_setting(&conn, "default_report_type", &serde_json::json!("YEAR"))?;
+
+    let settings = get_settings(&conn)?;
+    assert_eq!(settings.page_size, 50);
+    assert_eq!(settings.first_day_of_week, FirstDayOfWeek::SUNDAY);
+    assert!(settings.default_report_type == ReportType::YEAR);
+
+    let settings = reset_setting(&conn, "page_size")?;
+    assert_eq!(settings.page_size, Settings::default().page_size);
+    Ok(())
+}
+
+#[test]
+fn set_setting_should_validate_values() -> Result<(), Box<dyn Error>> {
+    let conn = init_db_in_memory()?;
+    assert!(set_setting(&conn, "page_size", &serde_json::json!(0)).is_err());
+    assert!(set_setting(&conn, "page_size", &serde_json::json!("ten")).is_err());
+    assert!(set_setting(&conn, "base_currency", &serde_json::json!("euro")).is_err());
+    assert!(set_setting(&conn, "date_format", &serde_json::json!("%Y-%Q")).is_err());
+    assert!(set_setting(&conn, "first_day_of_week", &serde_json::json!("FRIDAY")).is_err());
+    assert!(set_setting(&conn, "unknown", &serde_json::json!(1)).is_err());
+
+    set_setting(&conn, "date_format", &serde_json::json!("%d.%m.%Y"))?;
+    assert_eq!(get_settings(&conn)?.date_format, "%d.%m.%Y");
+    Ok(())
+}
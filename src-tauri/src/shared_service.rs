@@ -1,31 +1,103 @@
-use rusqlite::Connection;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use rusqlite::{named_params, Connection};
 use std::collections::HashMap;
-use crate::models::Category;
+use crate::models::{Category, Tag};
 
-pub fn query_transaction_category_rows(db: &Connection) -> Result<HashMap<i32, Vec<Category>>, rusqlite::Error> {
+// Runs the operation inside a savepoint so that either all or none of its changes are kept,
+// savepoints can be nested unlike transactions
+pub fn in_savepoint<T, F>(db: &Connection, operation: F) -> Result<T, rusqlite::Error>
+where
+    F: FnOnce() -> Result<T, rusqlite::Error>,
+{
+    db.execute_batch("SAVEPOINT operation;")?;
+    match operation() {
+        Ok(result) => {
+            db.execute_batch("RELEASE operation;")?;
+            Ok(result)
+        }
+        Err(e) => {
+            db.execute_batch("ROLLBACK TO operation; RELEASE operation;")?;
+            Err(e)
+        }
+    }
+}
+
+pub fn invalid_date(date: &str) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(format!("Invalid date: {:?}", date).into())
+}
+
+// Error of an argument outside of its allowed values, the message names the requirement
+pub fn invalid_input(message: &str) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(message.into())
+}
 
-    let mut transaction_category_rows_statement = db.prepare(
+// expected date is of format YYYY-MM-DD
+pub fn parse_date(date: &str) -> Result<NaiveDate, rusqlite::Error> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").map_err(|_| invalid_date(date))
+}
+
+// Dates of transactions are YYYY-MM-DD optionally followed by a time of day
+pub fn parse_date_time(date: &str) -> Result<(NaiveDate, Option<NaiveTime>), rusqlite::Error> {
+    let date = date.trim();
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(date, format) {
+            return Ok((date_time.date(), Some(date_time.time())));
+        }
+    }
+    Ok((parse_date(date)?, None))
+}
+
+// Stored form of a transaction date, sorts in chronological order as text
+pub fn format_date_time(date: NaiveDate, time: Option<NaiveTime>) -> String {
+    match time {
+        Some(time) => NaiveDateTime::new(date, time).format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => date.format("%Y-%m-%d").to_string(),
+    }
+}
+
+// Stored form of a date without a time of day
+pub fn format_date(date: NaiveDate) -> String {
+    format_date_time(date, None)
+}
+
+// Transactions on the given date are before the returned bound, also when they have a time
+pub fn end_of_day(date: &str) -> Result<String, rusqlite::Error> {
+    let date = parse_date(date)?;
+    let next_day = date.succ_opt().ok_or(invalid_date(&format_date(date)))?;
+    Ok(format_date(next_day))
+}
+
+// Value of an rarray parameter, e.g. "WHERE id IN rarray(:ids)"
+pub fn to_array(ids: &[i32]) -> std::rc::Rc<Vec<rusqlite::types::Value>> {
+    std::rc::Rc::new(ids.iter().map(|id| rusqlite::types::Value::from(*id)).collect())
+}
+
+pub fn query_transaction_category_rows(
+    db: &Connection,
+    transaction_ids: &[i32],
+) -> Result<HashMap<i32, Vec<Category>>, rusqlite::Error> {
+
+    // archived categories are still shown on the transactions they were used on
+    let mut transaction_category_rows_statement = db.prepare(&format!(
         "
-        SELECT ec.transaction_id, ec.category_id, c.label
+        SELECT ec.transaction_id, {}
         FROM transaction_categories ec
         JOIN categories c
-        ON c.id = ec.category_id;
+        ON c.id = ec.category_id
+        WHERE ec.transaction_id IN rarray(:ids) AND c.deleted_at IS NULL
+        ORDER BY c.sort_order, c.id;
         ",
-    )?;
+        crate::category_service::CATEGORY_COLUMNS
+    ))?;
 
-    let mut transaction_category_rows = transaction_category_rows_statement.query([])?;
+    let mut transaction_category_rows = transaction_category_rows_statement
+        .query(named_params! { ":ids": to_array(transaction_ids) })?;
 
     let mut transaction_category_labels: HashMap<i32, Vec<Category>> = HashMap::new();
 
     while let Some(row) = transaction_category_rows.next()? {
         let transaction_id: i32 = row.get(0)?;
-        let category_id: i32 = row.get(1)?;
-        let category_label: String = row.get(2)?;
-
-        let category = Category {
-            id: category_id,
-            label: category_label,
-        };
+        let category = crate::category_service::category_from_row(row, 1)?;
 
         transaction_category_labels
             .entry(transaction_id)
@@ -35,3 +107,67 @@ pub fn query_transaction_category_rows(db: &Connection) -> Result<HashMap<i32, V
 
     Ok(transaction_category_labels)
 }
+
+pub fn query_transaction_tag_rows(
+    db: &Connection,
+    transaction_ids: &[i32],
+) -> Result<HashMap<i32, Vec<Tag>>, rusqlite::Error> {
+    let mut transaction_tag_rows_statement = db.prepare(
+        "
+        SELECT tt.transaction_id, tt.tag_id, t.label
+        FROM transaction_tags tt
+        JOIN tags t
+        ON t.id = tt.tag_id
+        WHERE tt.transaction_id IN rarray(:ids);
+        ",
+    )?;
+
+    let mut transaction_tag_rows = transaction_tag_rows_statement
+        .query(named_params! { ":ids": to_array(transaction_ids) })?;
+
+    let mut transaction_tags: HashMap<i32, Vec<Tag>> = HashMap::new();
+
+    while let Some(row) = transaction_tag_rows.next()? {
+        let transaction_id: i32 = row.get(0)?;
+
+        transaction_tags.entry(transaction_id).or_default().push(Tag {
+            id: row.get(1)?,
+            label: row.get(2)?,
+        });
+    }
+
+    Ok(transaction_tags)
+}
+
+// Directory of a test below the temporary directory, it starts out empty and is removed
+// again when dropped
+#[cfg(test)]
+pub struct TestDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    pub fn new(name: &str) -> TestDir {
+        let dir = std::env::temp_dir()
+            .join("finance-app-tests")
+            .join(format!("{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -5,28 +5,31 @@ use tauri::{AppHandle, State, Manager};
 
 pub struct AppState {
   pub db: std::sync::Mutex<Option<Connection>>,
+  pub db_path: std::sync::Mutex<Option<std::path::PathBuf>>,
 }
 
+// Commands fail instead of panicking while no database is open, e.g. before an encrypted
+// database is unlocked
 pub trait ServiceAccess {
-  fn db<F, TResult>(&self, operation: F) -> TResult where F: FnOnce(&Connection) -> TResult;
+  fn db<F, T, E>(&self, operation: F) -> Result<T, String> where F: FnOnce(&Connection) -> Result<T, E>, E: ToString;
 
-  fn db_mut<F, TResult>(&self, operation: F) -> TResult where F: FnOnce(&mut Connection) -> TResult;
+  fn db_mut<F, T, E>(&self, operation: F) -> Result<T, String> where F: FnOnce(&mut Connection) -> Result<T, E>, E: ToString;
 }
 
 impl ServiceAccess for AppHandle {
-  fn db<F, TResult>(&self, operation: F) -> TResult where F: FnOnce(&Connection) -> TResult {
+  fn db<F, T, E>(&self, operation: F) -> Result<T, String> where F: FnOnce(&Connection) -> Result<T, E>, E: ToString {
     let app_state: State<AppState> = self.state();
     let db_connection_guard = app_state.db.lock().unwrap();
-    let db = db_connection_guard.as_ref().unwrap();
+    let db = db_connection_guard.as_ref().ok_or("No database is open".to_string())?;
   
-    operation(db)
+    operation(db).map_err(|e| e.to_string())
   }
 
-  fn db_mut<F, TResult>(&self, operation: F) -> TResult where F: FnOnce(&mut Connection) -> TResult {
+  fn db_mut<F, T, E>(&self, operation: F) -> Result<T, String> where F: FnOnce(&mut Connection) -> Result<T, E>, E: ToString {
     let app_state: State<AppState> = self.state();
     let mut db_connection_guard = app_state.db.lock().unwrap();
-    let db = db_connection_guard.as_mut().unwrap();
+    let db = db_connection_guard.as_mut().ok_or("No database is open".to_string())?;
   
-    operation(db)
+    operation(db).map_err(|e| e.to_string())
   }
 }
@@ -0,0 +1,215 @@
+use crate::journal_service::{Change, Entity};
+use crate::models;
+use rusqlite::{named_params, Connection};
+
+pub fn insert_tag(db: &Connection, label: &str) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "INSERT OR IGNORE INTO tags (label) VALUES (:label);",
+        named_params! {
+            ":label": label.to_lowercase().trim()
+        },
+    )?;
+    Ok(())
+}
+
+pub fn update_tag_label(db: &Connection, id: i32, label: &str) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "UPDATE tags SET label = (:label) WHERE id = (:id);",
+        named_params! {
+            ":label": label.to_lowercase().trim(),
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+// Tags themselves are not journaled, undoing an older change of a transaction does not bring
+// back links to a deleted tag
+pub fn delete_tag(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
+    db.execute(
+        "DELETE FROM tags WHERE id = (:id);",
+        named_params! {
+            ":id": id,
+        },
+    )?;
+    Ok(())
+}
+
+pub fn get_tags(db: &Connection) -> Result<Vec<models::Tag>, rusqlite::Error> {
+    let mut stmt = db.prepare("SELECT id, label FROM tags;")?;
+    let mut rows = stmt.query([])?;
+    let mut tags = Vec::new();
+    while let Some(row) = rows.next()? {
+        tags.push(models::Tag {
+            id: row.get(0)?,
+            label: row.get(1)?,
+        });
+    }
+    Ok(tags)
+}
+
+pub fn add_transaction_tag(
+    db: &Connection,
+    transaction_id: i32,
+    tag_id: i32,
+) -> Result<(), rusqlite::Error> {
+    change_transaction_tags(
+        db,
+        transaction_id,
+        tag_id,
+        "add_transaction_tag",
+        "
+        INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id)
+        SELECT (:transaction_id), id FROM tags WHERE id = (:tag_id);
+        ",
+    )
+}
+
+pub fn remove_transaction_tag(
+    db: &Connection,
+    transaction_id: i32,
+    tag_id: i32,
+) -> Result<(), rusqlite::Error> {
+    change_transaction_tags(
+        db,
+        transaction_id,
+        tag_id,
+        "remove_transaction_tag",
+        "
+        DELETE FROM transaction_tags
+        WHERE transaction_id = (:transaction_id) AND tag_id = (:tag_id);
+        ",
+    )
+}
+
+// Tag links are part of the journal snapshot of a transaction, so changing them is recorded
+// like any other change of the transaction
+fn change_transaction_tags(
+    db: &Connection,
+    transaction_id: i32,
+    tag_id: i32,
+    label: &str,
+    stmt: &str,
+) -> Result<(), rusqlite::Error> {
+    crate::shared_service::in_savepoint(db, || {
+        let before = crate::journal_service::snapshot(db, Entity::Transaction, transaction_id)?;
+        if before.is_none() {
+            return Ok(());
+        }
+
+        let changed = db.execute(
+            stmt,
+            named_params! {
+                ":transaction_id": transaction_id,
+                ":tag_id": tag_id,
+            },
+        )?;
+        if changed == 0 {
+            return Ok(());
+        }
+        db.execute(
+            "UPDATE transactions SET updated_at = CURRENT_TIMESTAMP WHERE id = (:id);",
+            named_params! { ":id": transaction_id },
+        )?;
+
+        crate::journal_service::record(
+            db,
+            label,
+            vec![Change {
+                entity: Entity::Transaction,
+                entity_id: transaction_id,
+                before,
+                after: crate::journal_service::snapshot(db, Entity::Transaction, transaction_id)?,
+            }],
+        )
+    })
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn insert_should_ignore_casing_and_whitespace() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_tag(&conn, "vacation-2026")?;
+    insert_tag(&conn, " Vacation-2026 ")?;
+    let list = get_tags(&conn)?;
+
+    assert!(list.len() == 1, "Expected list with one item, got {:?}", list);
+    assert_eq!(list[0].label, "vacation-2026");
+    Ok(())
+}
+
+#[test]
+fn should_be_able_to_change_label() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_tag(&conn, "test")?;
+    let id = get_tags(&conn)?[0].id;
+
+    update_tag_label(&conn, id, "reimbursable")?;
+
+    assert_eq!(get_tags(&conn)?[0].label, "reimbursable");
+    Ok(())
+}
+
+#[test]
+fn tagged_transactions_should_be_filterable() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_tag(&conn, "vacation")?;
+    insert_tag(&conn, "reimbursable")?;
+    let tags = get_tags(&conn)?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test1", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test2", None, "2023-11-01", None, vec![])?;
+
+    add_transaction_tag(&conn, 1, tags[0].id)?;
+    add_transaction_tag(&conn, 1, tags[0].id)?;
+    add_transaction_tag(&conn, 2, tags[1].id)?;
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![tags[0].id])?;
+    assert_eq!(page.transactions.len(), 1);
+    assert_eq!(page.transactions[0].name, "test1");
+    assert_eq!(page.transactions[0].tags.len(), 1);
+    assert_eq!(page.transactions[0].tags[0].label, "vacation");
+
+    remove_transaction_tag(&conn, 1, tags[0].id)?;
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![tags[0].id])?;
+    assert!(page.transactions.is_empty());
+    Ok(())
+}
+
+#[test]
+fn delete_should_remove_tag_from_transactions() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_tag(&conn, "vacation")?;
+    let id = get_tags(&conn)?[0].id;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
+    add_transaction_tag(&conn, 1, id)?;
+
+    delete_tag(&conn, id)?;
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert!(get_tags(&conn)?.is_empty());
+    assert!(page.transactions[0].tags.is_empty());
+    Ok(())
+}
+
+#[test]
+fn tag_changes_should_be_undoable() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_tag(&conn, "vacation")?;
+    crate::transaction_service::insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::set_transaction_bank_dates(&conn, 1, Some("2023-11-02"), None)?;
+    add_transaction_tag(&conn, 1, 1)?;
+
+    // undoing the tag keeps the earlier change
+    assert_eq!(crate::journal_service::undo(&conn)?, Some("add_transaction_tag".to_string()));
+    let transaction = crate::transaction_service::get_transaction(&conn, 1)?;
+    assert!(transaction.tags.is_empty());
+    assert!(transaction.booking_date.is_some());
+
+    crate::journal_service::redo(&conn)?;
+    remove_transaction_tag(&conn, 1, 1)?;
+    crate::journal_service::undo(&conn)?;
+    assert_eq!(crate::transaction_service::get_transaction(&conn, 1)?.tags.len(), 1);
+    Ok(())
+}
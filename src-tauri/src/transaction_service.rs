@@ -1,6 +1,81 @@
+use rusqlite::types::{ToSql, Value};
 use rusqlite::{named_params, Connection};
+use std::rc::Rc;
+
+use crate::journal_service::{Change, Entity};
+use crate::models::{
+    CursorPage, Page, PageCursor, Transaction, TransactionCursor, TransactionFilter,
+};
+use crate::shared_service::{format_date_time, parse_date, parse_date_time, to_array};
+
+// Balance of the account of the transaction t after it, transactions of an account are in the
+// order of date_created and id. Calculated per row so that only the listed rows are summed up.
+const RUNNING_BALANCE: &str = "
+        COALESCE((SELECT opening_balance FROM accounts WHERE id = t.account_id), 0) + (
+            SELECT SUM(previous.value)
+            FROM transactions previous
+            WHERE previous.account_id IS t.account_id
+            AND previous.date_created <= t.date_created
+            AND (previous.date_created < t.date_created OR previous.id <= t.id)
+            AND previous.deleted_at IS NULL
+        )";
+
+// Conditions of the transactions t matching a filter, the values are bound by FilterParams.
+// The transaction list and bulk operations select transactions the same way.
+pub const FILTER_CONDITIONS: &str = "
+        ((:len) = 0 OR t.id IN (
+            SELECT transaction_id
+            FROM transaction_categories
+            WHERE category_id IN rarray(:ids)
+        ))
+        AND ((:payees_len) = 0 OR t.payee_id IN rarray(:payee_ids))
+        AND ((:tags_len) = 0 OR t.id IN (
+            SELECT transaction_id
+            FROM transaction_tags
+            WHERE tag_id IN rarray(:tag_ids)
+        ))
+        AND (name LIKE (:x) OR description LIKE (:x))
+        AND t.deleted_at IS NULL
+        ";
+
+pub struct FilterParams {
+    search: String,
+    ids: Rc<Vec<Value>>,
+    len: usize,
+    payee_ids: Rc<Vec<Value>>,
+    payees_len: usize,
+    tag_ids: Rc<Vec<Value>>,
+    tags_len: usize,
+}
 
-use crate::models::{Page, Transaction};
+impl FilterParams {
+    pub fn new(filter: &TransactionFilter) -> FilterParams {
+        FilterParams {
+            search: format!("%{}%", filter.search.trim().replace("%", "")),
+            ids: to_array(&filter.categories),
+            len: filter.categories.len(),
+            payee_ids: to_array(&filter.payees),
+            payees_len: filter.payees.len(),
+            tag_ids: to_array(&filter.tags),
+            tags_len: filter.tags.len(),
+        }
+    }
+
+    // The filter values followed by the other parameters of the query
+    pub fn with<'a>(&'a self, params: &[(&'a str, &'a dyn ToSql)]) -> Vec<(&'a str, &'a dyn ToSql)> {
+        let mut all: Vec<(&str, &dyn ToSql)> = vec![
+            (":x", &self.search),
+            (":ids", &self.ids),
+            (":len", &self.len),
+            (":payee_ids", &self.payee_ids),
+            (":payees_len", &self.payees_len),
+            (":tag_ids", &self.tag_ids),
+            (":tags_len", &self.tags_len),
+        ];
+        all.extend_from_slice(params);
+        all
+    }
+}
 
 pub fn query_page(
     db: &Connection,
@@ -8,33 +83,19 @@ pub fn query_page(
     current_page: i32,
     search: &str,
     selected_categories: Vec<i32>,
+    selected_payees: Vec<i32>,
+    selected_tags: Vec<i32>,
 ) -> Result<Page, rusqlite::Error> {
-    let sanitized_search = format!("%{}%", search.trim().replace("%", ""));
-
-    let ids = &std::rc::Rc::new(
-        selected_categories
-            .clone()
-            .into_iter()
-            .map(rusqlite::types::Value::from)
-            .collect::<Vec<rusqlite::types::Value>>(),
-    );
+    let filter_params = FilterParams::new(&TransactionFilter {
+        search: search.to_string(),
+        categories: selected_categories,
+        payees: selected_payees,
+        tags: selected_tags,
+    });
 
     let count: i32 = db.query_row(
-        "
-        SELECT COUNT(*) 
-        FROM transactions as t
-        WHERE ((:len) = 0 OR t.id IN (
-            SELECT transaction_id 
-            FROM transaction_categories 
-            WHERE category_id IN rarray(:ids)
-        ))
-        AND (name LIKE (:x) OR description LIKE (:x))
-        ",
-        named_params! {
-            ":x": sanitized_search,
-            ":ids": ids,
-            ":len": ids.len(),
-        },
+        &format!("SELECT COUNT(*) FROM transactions as t WHERE {}", FILTER_CONDITIONS),
+        &*filter_params.with(&[]),
         |row| row.get(0),
     )?;
 
@@ -46,55 +107,44 @@ pub fn query_page(
 
     let mut transactions: Vec<Transaction> = Vec::new();
 
-    let mut transaction_rows_statement = db.prepare(
+    let mut transaction_rows_statement = db.prepare(&format!(
         "
         SELECT
         id,
         value,
         name,
         description,
-        date_created
-        FROM transactions as t
-        WHERE ((:len) = 0 OR t.id IN (
-            SELECT transaction_id 
-            FROM transaction_categories 
-            WHERE category_id IN rarray(:ids)
-        ))
-        AND (name LIKE (:x) OR description LIKE (:x))
-        ORDER BY date_created, id ASC 
-        LIMIT :page_size 
-        OFFSET :offset
+        date_created,
+        account_id,
+        payee_id,
+        {running_balance},
+        booking_date,
+        value_date,
+        created_at,
+        updated_at
+        FROM (
+            -- the page is selected first so that the running balance is only calculated for it
+            SELECT * FROM transactions as t
+            WHERE {conditions}
+            ORDER BY date_created, id ASC
+            LIMIT :page_size
+            OFFSET :offset
+        ) as t
+        ORDER BY date_created, id ASC
         ",
-    )?;
+        running_balance = RUNNING_BALANCE,
+        conditions = FILTER_CONDITIONS
+    ))?;
 
-    let mut transaction_rows = transaction_rows_statement.query(named_params! {
+    let mut transaction_rows = transaction_rows_statement.query(&*filter_params.with(named_params! {
         ":page_size": page_size,
         ":offset": (current_page - 1) * page_size,
-        ":x": sanitized_search,
-        ":ids": ids,
-        ":len": ids.len(),
-    })?;
-
-    let mut transaction_category_labels =
-        crate::shared_service::query_transaction_category_rows(db)?;
+    }))?;
 
     while let Some(row) = transaction_rows.next()? {
-        let id = row.get(0)?;
-
-        let categories = transaction_category_labels
-            .remove(&id)
-            .unwrap_or(Vec::new());
-
-        let transaction = Transaction {
-            id,
-            value: row.get(1)?,
-            name: row.get(2)?,
-            description: row.get(3)?,
-            date_created: row.get(4)?,
-            categories,
-        };
-        transactions.push(transaction);
+        transactions.push(transaction_from_row(row)?);
     }
+    load_links(db, &mut transactions)?;
 
     Ok(Page {
         total_pages,
@@ -102,83 +152,312 @@ pub fn query_page(
     })
 }
 
+// Page of transactions next to the cursor, without a cursor the first page is returned.
+// Unlike query_page the rows of a page do not shift when transactions are added in between.
+pub fn query_cursor_page(
+    db: &Connection,
+    page_size: i32,
+    filter: &TransactionFilter,
+    cursor: Option<&PageCursor>,
+    with_count: bool,
+) -> Result<CursorPage, rusqlite::Error> {
+    let filter_params = FilterParams::new(filter);
+
+    let total_count = if with_count {
+        let count: i32 = db.query_row(
+            &format!("SELECT COUNT(*) FROM transactions as t WHERE {}", FILTER_CONDITIONS),
+            &*filter_params.with(&[]),
+            |row| row.get(0),
+        )?;
+        Some(count)
+    } else {
+        None
+    };
+
+    // pages before the cursor are read backwards and reversed
+    let (comparison, order, position) = match cursor {
+        None => (">", "ASC", None),
+        Some(PageCursor::AFTER(position)) => (">", "ASC", Some(position)),
+        Some(PageCursor::BEFORE(position)) => ("<", "DESC", Some(position)),
+    };
+
+    let mut transaction_rows_statement = db.prepare(&format!(
+        "
+        SELECT
+        id,
+        value,
+        name,
+        description,
+        date_created,
+        account_id,
+        payee_id,
+        {running_balance},
+        booking_date,
+        value_date,
+        created_at,
+        updated_at
+        FROM (
+            SELECT * FROM transactions as t
+            WHERE {conditions}
+            AND ((:id) IS NULL OR (date_created, id) {comparison} ((:date_created), (:id)))
+            ORDER BY date_created {order}, id {order}
+            LIMIT :limit
+        ) as t
+        ORDER BY date_created {order}, id {order}
+        ",
+        running_balance = RUNNING_BALANCE,
+        conditions = FILTER_CONDITIONS
+    ))?;
+
+    // one extra row tells whether there is another page
+    let mut transaction_rows = transaction_rows_statement.query(&*filter_params.with(named_params! {
+        ":limit": page_size + 1,
+        ":date_created": position.map(|position| &position.date_created),
+        ":id": position.map(|position| position.id),
+    }))?;
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    while let Some(row) = transaction_rows.next()? {
+        transactions.push(transaction_from_row(row)?);
+    }
+
+    let has_more = transactions.len() > page_size as usize;
+    transactions.truncate(page_size.max(0) as usize);
+    load_links(db, &mut transactions)?;
+    if let Some(PageCursor::BEFORE(_)) = cursor {
+        transactions.reverse();
+    }
+
+    let to_cursor = |transaction: &Transaction| TransactionCursor {
+        date_created: format_date_time(transaction.date_created, transaction.time_created),
+        id: transaction.id,
+    };
+    let (has_next, has_previous) = match cursor {
+        None => (has_more, false),
+        Some(PageCursor::AFTER(_)) => (has_more, true),
+        Some(PageCursor::BEFORE(_)) => (true, has_more),
+    };
+
+    Ok(CursorPage {
+        next: transactions.last().filter(|_| has_next).map(to_cursor),
+        previous: transactions.first().filter(|_| has_previous).map(to_cursor),
+        transactions,
+        total_count,
+    })
+}
+
+// Columns are in the order of the transaction list query, categories and tags are added by
+// load_links
+fn transaction_from_row(row: &rusqlite::Row) -> Result<Transaction, rusqlite::Error> {
+    let (date_created, time_created) = parse_date_time(&row.get::<_, String>(4)?)?;
+
+    Ok(Transaction {
+        id: row.get(0)?,
+        value: row.get(1)?,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        date_created,
+        time_created,
+        booking_date: row.get::<_, Option<String>>(8)?.as_deref().map(parse_date).transpose()?,
+        value_date: row.get::<_, Option<String>>(9)?.as_deref().map(parse_date).transpose()?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        account_id: row.get(5)?,
+        payee_id: row.get(6)?,
+        running_balance: row.get(7)?,
+        categories: Vec::new(),
+        tags: Vec::new(),
+    })
+}
+
+// Categories and tags are only read for the given transactions instead of every transaction
+fn load_links(db: &Connection, transactions: &mut [Transaction]) -> Result<(), rusqlite::Error> {
+    let ids: Vec<i32> = transactions.iter().map(|transaction| transaction.id).collect();
+    let mut transaction_categories =
+        crate::shared_service::query_transaction_category_rows(db, &ids)?;
+    let mut transaction_tags = crate::shared_service::query_transaction_tag_rows(db, &ids)?;
+
+    for transaction in transactions.iter_mut() {
+        transaction.categories = transaction_categories
+            .remove(&transaction.id)
+            .unwrap_or_default();
+        transaction.tags = transaction_tags.remove(&transaction.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+// A single transaction as it would be listed, deleted transactions are not found
+pub fn get_transaction(db: &Connection, id: i32) -> Result<Transaction, rusqlite::Error> {
+    let mut transaction = db.query_row(
+        &format!(
+            "
+        SELECT
+        t.id,
+        t.value,
+        t.name,
+        t.description,
+        t.date_created,
+        t.account_id,
+        t.payee_id,
+        {},
+        t.booking_date,
+        t.value_date,
+        t.created_at,
+        t.updated_at
+        FROM transactions t
+        WHERE t.id = (:id) AND t.deleted_at IS NULL;
+        ",
+            RUNNING_BALANCE
+        ),
+        named_params! { ":id": id },
+        transaction_from_row,
+    )?;
+    load_links(db, std::slice::from_mut(&mut transaction))?;
+    Ok(transaction)
+}
+
+// Returns the stored transaction, categories which do not exist are not linked
 pub fn insert_transaction(
     db: &Connection,
     value: f64,
     name: &str,
     description: Option<&str>,
     date_created: &str,
+    account_id: Option<i32>,
     transaction_categories: Vec<i32>,
-) -> Result<(), rusqlite::Error> {
-    db.execute(
-        "
-        INSERT INTO transactions(
-            value,
-            name,
-            description,
-            date_created
-        )
-        VALUES(
-            :value,
-            :name,
-            :description,
-            :date_created
-        );
-        ",
-        named_params! {
-            ":value": value,
-            ":name": name,
-            ":description": description,
-            ":date_created": date_created
-        },
-    )?;
+) -> Result<Transaction, rusqlite::Error> {
+    let (date, time) = parse_date_time(date_created)?;
 
-    let transaction_id = db.last_insert_rowid();
-
-    for category in transaction_categories {
-        let category_exists: i32 = db.query_row(
+    crate::shared_service::in_savepoint(db, || {
+        db.execute(
             "
-            SELECT COUNT(*) FROM categories WHERE id = :id;
+            INSERT INTO transactions(
+                value,
+                name,
+                description,
+                date_created,
+                account_id,
+                payee_id,
+                created_at,
+                updated_at
+            )
+            VALUES(
+                :value,
+                :name,
+                :description,
+                :date_created,
+                :account_id,
+                :payee_id,
+                CURRENT_TIMESTAMP,
+                CURRENT_TIMESTAMP
+            );
             ",
             named_params! {
-                ":id": category,
+                ":value": value,
+                ":name": name,
+                ":description": description,
+                ":date_created": format_date_time(date, time),
+                ":account_id": account_id,
+                ":payee_id": crate::payee_service::resolve_payee(db, name)?
             },
-            |row| row.get(0),
         )?;
 
-        // If the category does not exist, skip it
-        if category_exists == 0 {
-            continue;
+        let transaction_id = db.last_insert_rowid() as i32;
+
+        for category in transaction_categories {
+            let category_exists: i32 = db.query_row(
+                "
+                SELECT COUNT(*) FROM categories WHERE id = :id AND deleted_at IS NULL;
+                ",
+                named_params! {
+                    ":id": category,
+                },
+                |row| row.get(0),
+            )?;
+
+            // If the category does not exist, skip it
+            if category_exists == 0 {
+                continue;
+            }
+
+            db.execute(
+                "
+                INSERT INTO transaction_categories(
+                    transaction_id, 
+                    category_id
+                ) 
+                VALUES(
+                    :transaction_id, 
+                    :category_id
+                );",
+                named_params! {
+                    ":transaction_id": transaction_id,
+                    ":category_id": category,
+                },
+            )?;
+        }
+
+        crate::journal_service::record(
+            db,
+            "insert_transaction",
+            vec![Change {
+                entity: Entity::Transaction,
+                entity_id: transaction_id,
+                before: None,
+                after: crate::journal_service::snapshot(db, Entity::Transaction, transaction_id)?,
+            }],
+        )?;
+        get_transaction(db, transaction_id)
+    })
+}
+
+// Booking and value dates as provided by banks, None clears the date
+pub fn set_transaction_bank_dates(
+    db: &Connection,
+    id: i32,
+    booking_date: Option<&str>,
+    value_date: Option<&str>,
+) -> Result<(), rusqlite::Error> {
+    let booking_date = booking_date.map(parse_date).transpose()?;
+    let value_date = value_date.map(parse_date).transpose()?;
+
+    crate::shared_service::in_savepoint(db, || {
+        let before = crate::journal_service::snapshot(db, Entity::Transaction, id)?;
+        if before.is_none() {
+            return Ok(());
         }
 
         db.execute(
             "
-            INSERT INTO transaction_categories(
-                transaction_id, 
-                category_id
-            ) 
-            VALUES(
-                :transaction_id, 
-                :category_id
-            );",
+            UPDATE transactions
+            SET booking_date = (:booking_date),
+            value_date = (:value_date),
+            updated_at = CURRENT_TIMESTAMP
+            WHERE id = (:id);
+            ",
             named_params! {
-                ":transaction_id": transaction_id,
-                ":category_id": category,
+                ":booking_date": booking_date.map(|date| date.format("%Y-%m-%d").to_string()),
+                ":value_date": value_date.map(|date| date.format("%Y-%m-%d").to_string()),
+                ":id": id,
             },
         )?;
-    }
 
-    Ok(())
+        crate::journal_service::record(
+            db,
+            "set_transaction_bank_dates",
+            vec![Change {
+                entity: Entity::Transaction,
+                entity_id: id,
+                before,
+                after: crate::journal_service::snapshot(db, Entity::Transaction, id)?,
+            }],
+        )
+    })
 }
 
+// The transaction is moved to the trash, see trash_service
 pub fn delete_transaction(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
-    db.execute(
-        "DELETE FROM transactions WHERE id = :id",
-        named_params! {
-            ":id": id,
-        },
-    )?;
-
+    crate::trash_service::move_to_trash(db, Entity::Transaction, id, "delete_transaction")?;
     Ok(())
 }
 
@@ -188,14 +467,14 @@ use crate::migration_service::init_db_in_memory;
 #[test]
 fn insert_should_succeed() -> Result<(), rusqlite::Error> {
     let conn = init_db_in_memory()?;
-    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![])?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
     Ok(())
 }
 
 #[test]
 fn query_should_return_nil_when_new() -> Result<(), rusqlite::Error> {
     let conn = init_db_in_memory()?;
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
 
     assert!(
         page.transactions.len() == 0,
@@ -213,8 +492,8 @@ fn query_should_return_nil_when_new() -> Result<(), rusqlite::Error> {
 #[test]
 fn query_should_return_entry_after_insert() -> Result<(), rusqlite::Error> {
     let conn = init_db_in_memory()?;
-    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![])?;
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
 
     assert!(
         page.transactions.len() == 1,
@@ -232,9 +511,9 @@ fn query_should_return_entry_after_insert() -> Result<(), rusqlite::Error> {
 #[test]
 fn query_should_return_empty_page_if_added_entry_is_removed() -> Result<(), rusqlite::Error> {
     let conn = init_db_in_memory()?;
-    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![])?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
     delete_transaction(&conn, 1)?;
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
 
     assert!(
         page.transactions.len() == 0,
@@ -252,8 +531,8 @@ fn query_should_return_empty_page_if_added_entry_is_removed() -> Result<(), rusq
 #[test]
 fn inserting_missing_categories_should_not_result_in_failure() -> Result<(), rusqlite::Error> {
     let conn = init_db_in_memory()?;
-    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![1, 2])?;
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![1, 2])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
 
     assert!(
         page.transactions.len() == 1,
@@ -272,8 +551,8 @@ fn inserting_missing_categories_should_not_result_in_failure() -> Result<(), rus
 fn querying_inserted_entry_with_non_existing_categories_should_return_empty_category_list(
 ) -> Result<(), rusqlite::Error> {
     let conn = init_db_in_memory()?;
-    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![1, 2])?;
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![1, 2])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
 
     assert!(
         page.transactions.len() == 1,
@@ -301,9 +580,9 @@ fn querying_transaction_when_category_exists_should_return_correct_list(
     crate::category_service::insert_category(&conn, "test")?;
     let category = &crate::category_service::get_categories(&conn)?[0];
 
-    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![category.id])?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![category.id])?;
 
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
     assert!(
         page.transactions.len() == 1,
         "Expected 1 entry, got {:?}",
@@ -353,9 +632,10 @@ fn querying_transaction_after_category_removal_should_reflect_the_change(
         "test",
         None,
         "2023-11-01",
+        None,
         vec![category_1.id, category_2.id],
     )?;
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
     assert!(
         page.transactions.len() == 1,
         "Expected 1 entry, got {:?}",
@@ -369,7 +649,7 @@ fn querying_transaction_after_category_removal_should_reflect_the_change(
 
     crate::category_service::delete_category(&conn, category_1.id)?;
 
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
     assert!(
         page.transactions.len() == 1,
         "Expected 1 entry, got {:?}",
@@ -410,23 +690,23 @@ fn should_be_able_to_query_by_category() -> Result<(), rusqlite::Error> {
     let category_1 = &crate::category_service::get_categories(&conn)?[0];
     let category_2 = &crate::category_service::get_categories(&conn)?[1];
 
-    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", vec![category_1.id])?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![category_1.id])?;
 
-    let page = query_page(&conn, 10, 1, "", vec![])?;
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
     assert!(
         page.transactions.len() == 1,
         "Expected 1 entry, got {:?}",
         page.transactions
     );
 
-    let filter_page = query_page(&conn, 10, 1, "", vec![category_1.id])?;
+    let filter_page = query_page(&conn, 10, 1, "", vec![category_1.id], vec![], vec![])?;
     assert!(
         filter_page.transactions.len() == 1,
         "Expected 1 entry, got {:?}",
         filter_page.transactions
     );
 
-    let filter_page = query_page(&conn, 10, 1, "", vec![category_2.id])?;
+    let filter_page = query_page(&conn, 10, 1, "", vec![category_2.id], vec![], vec![])?;
     assert!(
         filter_page.transactions.len() == 0,
         "Expected 0 entries, got {:?} by category id {:?}",
@@ -446,7 +726,7 @@ fn should_be_able_to_query_by_multiple_categories() -> Result<(), rusqlite::Erro
     let category_1 = &crate::category_service::get_categories(&conn)?[0];
     let category_2 = &crate::category_service::get_categories(&conn)?[1];
 
-    insert_transaction(&conn, 1.0, "test1", None, "2023-11-01", vec![category_1.id])?;
+    insert_transaction(&conn, 1.0, "test1", None, "2023-11-01", None, vec![category_1.id])?;
 
     insert_transaction(
         &conn,
@@ -454,21 +734,22 @@ fn should_be_able_to_query_by_multiple_categories() -> Result<(), rusqlite::Erro
         "test2",
         None,
         "2023-11-01",
+        None,
         vec![category_1.id, category_2.id],
     )?;
 
-    insert_transaction(&conn, 1.0, "test3", None, "2023-11-01", vec![category_2.id])?;
+    insert_transaction(&conn, 1.0, "test3", None, "2023-11-01", None, vec![category_2.id])?;
 
-    insert_transaction(&conn, 1.0, "test4", None, "2023-11-01", vec![])?;
+    insert_transaction(&conn, 1.0, "test4", None, "2023-11-01", None, vec![])?;
 
-    let filter_page = query_page(&conn, 10, 1, "", vec![])?;
+    let filter_page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
     assert!(
         filter_page.transactions.len() == 4,
         "Expected 4 entry, got {:?}",
         filter_page.transactions
     );
 
-    let filter_page = query_page(&conn, 10, 1, "", vec![category_1.id, category_2.id])?;
+    let filter_page = query_page(&conn, 10, 1, "", vec![category_1.id, category_2.id], vec![], vec![])?;
     assert!(
         filter_page.transactions.len() == 3,
         "Expected 3 entry, got {:?} {:?}",
@@ -476,7 +757,7 @@ fn should_be_able_to_query_by_multiple_categories() -> Result<(), rusqlite::Erro
         filter_page.transactions
     );
 
-    let filter_page = query_page(&conn, 10, 1, "", vec![category_1.id])?;
+    let filter_page = query_page(&conn, 10, 1, "", vec![category_1.id], vec![], vec![])?;
     assert!(
         filter_page.transactions.len() == 2,
         "Expected 2 entry, got {:?} {:?}",
@@ -484,7 +765,7 @@ fn should_be_able_to_query_by_multiple_categories() -> Result<(), rusqlite::Erro
         filter_page.transactions
     );
 
-    let filter_page = query_page(&conn, 10, 1, "", vec![category_2.id])?;
+    let filter_page = query_page(&conn, 10, 1, "", vec![category_2.id], vec![], vec![])?;
     assert!(
         filter_page.transactions.len() == 2,
         "Expected 2 entry, got {:?} {:?}",
@@ -494,3 +775,184 @@ fn should_be_able_to_query_by_multiple_categories() -> Result<(), rusqlite::Erro
 
     Ok(())
 }
+
+#[test]
+fn query_should_return_running_balance_per_account() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::account_service::insert_account(&conn, "checking", 100.0)?;
+    let account = &crate::account_service::get_accounts(&conn)?[0];
+
+    insert_transaction(&conn, -10.0, "a", None, "2023-11-02", Some(account.id), vec![])?;
+    insert_transaction(&conn, 5.0, "b", None, "2023-11-01", Some(account.id), vec![])?;
+    insert_transaction(&conn, 1.0, "c", None, "2023-11-01", None, vec![])?;
+
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    let balances: Vec<(String, f64)> = page
+        .transactions
+        .iter()
+        .map(|t| (t.name.clone(), t.running_balance))
+        .collect();
+
+    assert_eq!(
+        balances,
+        vec![
+            ("b".to_string(), 105.0),
+            ("c".to_string(), 1.0),
+            ("a".to_string(), 95.0)
+        ]
+    );
+
+    // filtering should not affect the balance
+    let page = query_page(&conn, 10, 1, "a", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions.len(), 1);
+    assert_eq!(page.transactions[0].running_balance, 95.0);
+    Ok(())
+}
+
+#[test]
+fn insert_should_resolve_payee() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::payee_service::insert_payee(&conn, "Amazon")?;
+    crate::payee_service::insert_payee(&conn, "Grocery")?;
+    let payees = crate::payee_service::get_payees(&conn)?;
+    crate::payee_service::add_payee_pattern(&conn, payees[0].id, "amazon*")?;
+
+    insert_transaction(&conn, 1.0, "AMAZON MKTPLACE 123", None, "2023-11-01", None, vec![])?;
+    insert_transaction(&conn, 1.0, "grocery", None, "2023-11-01", None, vec![])?;
+    insert_transaction(&conn, 1.0, "unknown", None, "2023-11-01", None, vec![])?;
+
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    let payee_ids: Vec<Option<i32>> = page.transactions.iter().map(|t| t.payee_id).collect();
+    assert_eq!(payee_ids, vec![Some(payees[0].id), Some(payees[1].id), None]);
+
+    let filter_page = query_page(&conn, 10, 1, "", vec![], vec![payees[1].id], vec![])?;
+    assert_eq!(filter_page.transactions.len(), 1);
+    assert_eq!(filter_page.transactions[0].name, "grocery");
+    Ok(())
+}
+
+#[test]
+fn insert_should_reject_invalid_date() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    assert!(insert_transaction(&conn, 1.0, "test", None, "2023-02-30", None, vec![]).is_err());
+    assert!(insert_transaction(&conn, 1.0, "test", None, "01.11.2023", None, vec![]).is_err());
+
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert!(page.transactions.is_empty());
+    Ok(())
+}
+
+#[test]
+fn insert_should_keep_time_of_day() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01T10:30", None, vec![])?;
+
+    let transaction = &query_page(&conn, 10, 1, "", vec![], vec![], vec![])?.transactions[0];
+    assert_eq!(transaction.date_created, chrono::NaiveDate::from_ymd_opt(2023, 11, 1).unwrap());
+    assert_eq!(transaction.time_created, chrono::NaiveTime::from_hms_opt(10, 30, 0));
+    Ok(())
+}
+
+#[test]
+fn bank_dates_should_be_set_and_validated() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    insert_transaction(&conn, 1.0, "test", None, "2023-11-01", None, vec![])?;
+    assert!(set_transaction_bank_dates(&conn, 1, Some("2023-11-31"), None).is_err());
+    set_transaction_bank_dates(&conn, 1, Some("2023-11-02"), Some("2023-11-03"))?;
+
+    let transaction = &query_page(&conn, 10, 1, "", vec![], vec![], vec![])?.transactions[0];
+    assert_eq!(transaction.booking_date, Some(parse_date("2023-11-02")?));
+    assert_eq!(transaction.value_date, Some(parse_date("2023-11-03")?));
+    assert!(transaction.created_at.is_some());
+    assert!(transaction.updated_at.is_some());
+    Ok(())
+}
+
+#[test]
+fn insert_should_return_stored_transaction() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::account_service::insert_account(&conn, "bank", 100.0)?;
+    crate::category_service::insert_category(&conn, "food")?;
+    insert_transaction(&conn, -10.0, "first", None, "2023-11-01", Some(1), vec![])?;
+
+    let transaction = insert_transaction(&conn, -5.0, " store ", Some("milk"), "2023-11-02T10:30", Some(1), vec![1, 7])?;
+    assert_eq!(transaction.id, 2);
+    assert_eq!(transaction.date_created.to_string(), "2023-11-02");
+    assert_eq!(transaction.time_created.map(|time| time.to_string()).as_deref(), Some("10:30:00"));
+    assert_eq!(transaction.running_balance, 85.0);
+    assert!(transaction.created_at.is_some());
+    // the unknown category is not linked
+    assert_eq!(transaction.categories.len(), 1);
+    assert_eq!(transaction.categories[0].label, "food");
+
+    let page = query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions[1].running_balance, transaction.running_balance);
+    assert_eq!(page.transactions[1].name, transaction.name);
+    Ok(())
+}
+
+#[test]
+fn cursor_pages_should_not_shift_after_insert() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    for day in 1..=5 {
+        insert_transaction(&conn, -1.0, "test", None, &format!("2023-11-0{}", day), None, vec![])?;
+    }
+    let filter = TransactionFilter::default();
+
+    let first = query_cursor_page(&conn, 2, &filter, None, true)?;
+    assert_eq!(first.total_count, Some(5));
+    assert_eq!(first.previous, None);
+    let ids: Vec<i32> = first.transactions.iter().map(|t| t.id).collect();
+    assert_eq!(ids, vec![1, 2]);
+
+    // a transaction before the cursor does not move the next page
+    insert_transaction(&conn, -1.0, "test", None, "2023-10-01", None, vec![])?;
+    let next = first.next.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    let second = query_cursor_page(&conn, 2, &filter, Some(&PageCursor::AFTER(next)), false)?;
+    assert_eq!(second.total_count, None);
+    let ids: Vec<i32> = second.transactions.iter().map(|t| t.id).collect();
+    assert_eq!(ids, vec![3, 4]);
+
+    let next = second.next.clone().ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    let last = query_cursor_page(&conn, 2, &filter, Some(&PageCursor::AFTER(next)), false)?;
+    assert_eq!(last.transactions.len(), 1);
+    assert_eq!(last.next, None);
+
+    let previous = second.previous.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    let back = query_cursor_page(&conn, 2, &filter, Some(&PageCursor::BEFORE(previous)), false)?;
+    let ids: Vec<i32> = back.transactions.iter().map(|t| t.id).collect();
+    assert_eq!(ids, vec![1, 2]);
+    // the transaction inserted before the first page is there when going back
+    let previous = back.previous.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+    let start = query_cursor_page(&conn, 2, &filter, Some(&PageCursor::BEFORE(previous)), false)?;
+    assert_eq!(start.transactions[0].id, 6);
+    assert!(start.previous.is_none());
+    assert!(start.next.is_some());
+    Ok(())
+}
+
+#[test]
+fn tag_and_trash_changes_should_update_timestamp() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::tag_service::insert_tag(&conn, "vacation")?;
+    insert_transaction(&conn, -10.0, "hotel", None, "2023-11-01", None, vec![])?;
+    let outdated = Some("2000-01-01 00:00:00".to_string());
+    let reset = || conn.execute("UPDATE transactions SET updated_at = '2000-01-01 00:00:00';", []);
+
+    reset()?;
+    crate::tag_service::add_transaction_tag(&conn, 1, 1)?;
+    let tagged = get_transaction(&conn, 1)?.updated_at;
+    assert_ne!(tagged, outdated);
+
+    // undo and redo put back the exact timestamps of before and after the change
+    crate::journal_service::undo(&conn)?;
+    assert_eq!(get_transaction(&conn, 1)?.updated_at, outdated);
+    crate::journal_service::redo(&conn)?;
+    assert_eq!(get_transaction(&conn, 1)?.updated_at, tagged);
+
+    delete_transaction(&conn, 1)?;
+    reset()?;
+    crate::trash_service::restore_transaction(&conn, 1)?;
+    assert_ne!(get_transaction(&conn, 1)?.updated_at, outdated);
+    Ok(())
+}
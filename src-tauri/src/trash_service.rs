@@ -0,0 +1,212 @@
+use crate::journal_service::{Change, Entity};
+use crate::models::{Trash, TrashedCategory, TrashedTransaction};
+use crate::shared_service::parse_date_time;
+use rusqlite::{named_params, Connection};
+
+// Days a deleted row is kept in the trash before it is purged
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+// Marks the row as deleted, returns false when there was no row to delete
+pub fn move_to_trash(
+    db: &Connection,
+    entity: Entity,
+    id: i32,
+    label: &str,
+) -> Result<bool, rusqlite::Error> {
+    set_deleted(db, entity, id, label, true)
+}
+
+fn set_deleted(
+    db: &Connection,
+    entity: Entity,
+    id: i32,
+    label: &str,
+    deleted: bool,
+) -> Result<bool, rusqlite::Error> {
+    crate::shared_service::in_savepoint(db, || {
+        let before = crate::journal_service::snapshot(db, entity, id)?;
+
+        // only transactions keep track of when they were changed
+        let update = match (deleted, entity) {
+            (true, _) => "SET deleted_at = CURRENT_TIMESTAMP WHERE id = (:id) AND deleted_at IS NULL",
+            (false, Entity::Transaction) => {
+                "SET deleted_at = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = (:id) AND deleted_at IS NOT NULL"
+            }
+            (false, Entity::Category) => "SET deleted_at = NULL WHERE id = (:id) AND deleted_at IS NOT NULL",
+        };
+        let changed = db.execute(
+            &format!("UPDATE {} {};", entity.table(), update),
+            named_params! {
+                ":id": id,
+            },
+        )?;
+        if changed == 0 {
+            return Ok(false);
+        }
+
+        crate::journal_service::record(
+            db,
+            label,
+            vec![Change {
+                entity,
+                entity_id: id,
+                before,
+                after: crate::journal_service::snapshot(db, entity, id)?,
+            }],
+        )?;
+        Ok(true)
+    })
+}
+
+pub fn restore_transaction(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
+    set_deleted(db, Entity::Transaction, id, "restore_transaction", false)?;
+    Ok(())
+}
+
+// The category is restored together with its links to transactions
+pub fn restore_category(db: &Connection, id: i32) -> Result<(), rusqlite::Error> {
+    set_deleted(db, Entity::Category, id, "restore_category", false)?;
+    Ok(())
+}
+
+pub fn get_trash(db: &Connection) -> Result<Trash, rusqlite::Error> {
+    let mut stmt = db.prepare(
+        "
+        SELECT id, value, name, description, date_created, deleted_at
+        FROM transactions
+        WHERE deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC, id DESC;
+        ",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut transactions = Vec::new();
+    while let Some(row) = rows.next()? {
+        let (date_created, time_created) = parse_date_time(&row.get::<_, String>(4)?)?;
+        transactions.push(TrashedTransaction {
+            id: row.get(0)?,
+            value: row.get(1)?,
+            name: row.get(2)?,
+            description: row.get(3)?,
+            date_created,
+            time_created,
+            deleted_at: row.get(5)?,
+        });
+    }
+
+    let mut stmt = db.prepare(
+        "
+        SELECT id, label, deleted_at
+        FROM categories
+        WHERE deleted_at IS NOT NULL
+        ORDER BY deleted_at DESC, id DESC;
+        ",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut categories = Vec::new();
+    while let Some(row) = rows.next()? {
+        categories.push(TrashedCategory {
+            id: row.get(0)?,
+            label: row.get(1)?,
+            deleted_at: row.get(2)?,
+        });
+    }
+
+    Ok(Trash {
+        transactions,
+        categories,
+    })
+}
+
+// Permanently deletes rows that have been in the trash for longer than the retention period,
+// a retention of zero days empties the whole trash. Returns the number of purged rows.
+pub fn purge_trash(db: &Connection, retention_days: u32) -> Result<usize, rusqlite::Error> {
+    let modifier = format!("-{} days", retention_days);
+    crate::shared_service::in_savepoint(db, || {
+        let mut purged = 0;
+        for entity in [Entity::Transaction, Entity::Category] {
+            purged += db.execute(
+                &format!(
+                    "DELETE FROM {} WHERE deleted_at <= datetime('now', (:modifier));",
+                    entity.table()
+                ),
+                named_params! {
+                    ":modifier": modifier,
+                },
+            )?;
+        }
+        Ok(purged)
+    })
+}
+
+#[cfg(test)]
+use crate::migration_service::init_db_in_memory;
+
+#[test]
+fn deleted_transaction_should_be_listed_in_trash() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::delete_transaction(&conn, 1)?;
+
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert!(page.transactions.is_empty());
+
+    let trash = get_trash(&conn)?;
+    assert_eq!(trash.transactions.len(), 1);
+    assert_eq!(trash.transactions[0].id, 1);
+    assert_eq!(trash.transactions[0].name, "test");
+
+    let report = crate::report_service::get_basic_report(&conn, crate::models::ReportType::MONTH, "2023-11-01")?;
+    assert_eq!(report.total, 0.0);
+    Ok(())
+}
+
+#[test]
+fn restored_category_should_keep_its_transactions() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "test", None, "2023-11-01", None, vec![1])?;
+    crate::category_service::delete_category(&conn, 1)?;
+
+    assert!(crate::category_service::get_categories(&conn)?.is_empty());
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert!(page.transactions[0].categories.is_empty());
+    assert_eq!(get_trash(&conn)?.categories[0].label, "food");
+
+    restore_category(&conn, 1)?;
+    let page = crate::transaction_service::query_page(&conn, 10, 1, "", vec![], vec![], vec![])?;
+    assert_eq!(page.transactions[0].categories.len(), 1);
+    assert!(get_trash(&conn)?.categories.is_empty());
+    Ok(())
+}
+
+#[test]
+fn inserting_trashed_label_should_restore_category() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::category_service::insert_category(&conn, "food")?;
+    crate::category_service::delete_category(&conn, 1)?;
+    crate::category_service::insert_category(&conn, "Food")?;
+
+    let categories = crate::category_service::get_categories(&conn)?;
+    assert_eq!(categories.len(), 1);
+    assert_eq!(categories[0].id, 1);
+    Ok(())
+}
+
+#[test]
+fn purge_should_respect_retention() -> Result<(), rusqlite::Error> {
+    let conn = init_db_in_memory()?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "old", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::insert_transaction(&conn, -10.0, "new", None, "2023-11-01", None, vec![])?;
+    crate::transaction_service::delete_transaction(&conn, 1)?;
+    crate::transaction_service::delete_transaction(&conn, 2)?;
+    conn.execute("UPDATE transactions SET deleted_at = datetime('now', '-40 days') WHERE id = 1;", [])?;
+
+    assert_eq!(purge_trash(&conn, DEFAULT_RETENTION_DAYS)?, 1);
+    let trash = get_trash(&conn)?;
+    assert_eq!(trash.transactions.len(), 1);
+    assert_eq!(trash.transactions[0].name, "new");
+
+    assert_eq!(purge_trash(&conn, 0)?, 1);
+    assert!(get_trash(&conn)?.transactions.is_empty());
+    Ok(())
+}